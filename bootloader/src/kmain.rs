@@ -6,7 +6,9 @@ extern crate pi;
 pub mod lang_items;
 
 use pi::*;
+use pi::generic_timer::PhysicalTimer;
 use std::io;
+use std::time::Duration;
 
 /// Start address of the binary to load and of the bootloader.
 const BINARY_START_ADDR: usize = 0x80000;
@@ -30,11 +32,12 @@ fn jump_to(addr: *mut u8) -> ! {
 
 struct LED {
     pin: gpio::Gpio<gpio::Output>,
+    timer: PhysicalTimer,
 }
 
 impl LED {
-    fn new(pin: u8) -> LED {
-        LED { pin: gpio::Gpio::new(pin).into_output() }
+    fn new(pin: gpio::Gpio<gpio::Uninitialized>, timer: PhysicalTimer) -> LED {
+        LED { pin: pin.into_output(), timer: timer }
     }
 
     fn on(&mut self) {
@@ -47,25 +50,27 @@ impl LED {
 
     fn blink_for(&mut self, duration: u64) {
         self.on();
-        pi::timer::spin_sleep_ms(duration);
+        self.timer.sleep(Duration::from_millis(duration));
         self.off();
-        pi::timer::spin_sleep_ms(duration);
+        self.timer.sleep(Duration::from_millis(duration));
     }
 }
 
 #[no_mangle]
 pub extern "C" fn kmain() {
-    let mut led = LED::new(16);
+    let Peripherals { mut pins, uart, .. } = Peripherals::take().unwrap();
+    let (tx, rx) = (pins.take(14).unwrap(), pins.take(15).unwrap());
+    let mut mu = uart.into_mini_uart(tx, rx);
+    mu.set_read_timeout(750);
+
+    let timer = unsafe { PhysicalTimer::new() };
+    let mut led = LED::new(pins.take(16).unwrap(), timer);
     for _ in 0..3 {
         led.blink_for(300);
     }
     loop {
         match xmodem::Xmodem::receive(
-            {
-                let mut mu = uart::MiniUart::new();
-                mu.set_read_timeout(750);
-                mu
-            },
+            &mut mu,
             unsafe { std::slice::from_raw_parts_mut(BINARY_START, MAX_BINARY_SIZE) },
         ) {
             Err(ref e) if e.kind() == io::ErrorKind::TimedOut => {
@@ -1,66 +1,153 @@
 use std::io;
 use std::fmt;
 
-use pi::uart::MiniUart;
+use pi::uart::{MiniUart, BufferedUart, Pl011, LineError};
 
 use mutex::Mutex;
+use terminal::Terminal;
+
+/// The UART a `Console` reads from and writes to.
+pub enum Backend {
+    MiniUart(MiniUart),
+    Buffered(BufferedUart),
+    Pl011(Pl011),
+}
+
+impl From<MiniUart> for Backend {
+    fn from(uart: MiniUart) -> Backend {
+        Backend::MiniUart(uart)
+    }
+}
+
+impl From<BufferedUart> for Backend {
+    fn from(uart: BufferedUart) -> Backend {
+        Backend::Buffered(uart)
+    }
+}
+
+impl From<Pl011> for Backend {
+    fn from(uart: Pl011) -> Backend {
+        Backend::Pl011(uart)
+    }
+}
 
 /// A global singleton allowing read/write access to the console.
+///
+/// Input is read from the UART. Output is written to the UART and, once a
+/// screen is attached, mirrored to it.
 pub struct Console {
-    inner: Option<MiniUart>,
+    inner: Option<Backend>,
+    screen: Option<Terminal>,
 }
 
 impl Console {
     /// Creates a new instance of `Console`.
     const fn new() -> Console {
-        Console { inner: None }
+        Console { inner: None, screen: None }
     }
 
-    /// Initializes the console if it's not already initialized.
-    #[inline]
-    fn initialize(&mut self) {
-        if let None = self.inner {
-            self.inner = Some(MiniUart::new());
-        }
+    /// Initializes the console to read from and write to `uart`: a
+    /// `MiniUart`, a `BufferedUart` or a `Pl011`.
+    pub fn initialize<U: Into<Backend>>(&mut self, uart: U) {
+        self.inner = Some(uart.into());
     }
 
-    /// Returns a mutable borrow to the inner `MiniUart`, initializing it as
-    /// needed.
-    fn inner(&mut self) -> &mut MiniUart {
-        self.initialize();
-        self.inner.as_mut().unwrap()
+    /// Mirrors everything written to the console from now on to `screen`.
+    /// Replaces any screen attached before.
+    pub fn attach_screen(&mut self, screen: Terminal) {
+        self.screen = Some(screen);
+    }
+
+    /// Stops mirroring output to the screen and returns it, if one was
+    /// attached.
+    pub fn detach_screen(&mut self) -> Option<Terminal> {
+        self.screen.take()
+    }
+
+    /// Returns a mutable borrow to the inner UART.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the console has not been initialized.
+    fn inner(&mut self) -> &mut Backend {
+        self.inner.as_mut().expect("console used before initialization")
     }
 
     /// Reads a byte from the UART device, blocking until a byte is available.
     pub fn read_byte(&mut self) -> u8 {
-        self.inner().read_byte()
+        match *self.inner() {
+            Backend::MiniUart(ref mut uart) => uart.read_byte(),
+            Backend::Buffered(ref mut uart) => uart.read_byte(),
+            Backend::Pl011(ref mut uart) => uart.read_byte(),
+        }
     }
 
-    /// Writes the byte `byte` to the UART device.
+    /// Writes the byte `byte` to the UART device and the screen.
     pub fn write_byte(&mut self, byte: u8) {
-        self.inner().write_byte(byte)
+        match *self.inner() {
+            Backend::MiniUart(ref mut uart) => uart.write_byte(byte),
+            Backend::Buffered(ref mut uart) => uart.write_byte(byte),
+            Backend::Pl011(ref mut uart) => uart.write_byte(byte),
+        }
+        self.mirror(&[byte]);
+    }
+
+    /// Writes `bytes` to the screen, if one is attached.
+    fn mirror(&mut self, bytes: &[u8]) {
+        if let Some(ref mut screen) = self.screen {
+            screen.write_bytes(bytes);
+        }
+    }
+
+    /// Returns the receive line error seen since the last call, if any. An
+    /// error means input bytes were dropped or corrupted.
+    pub fn take_error(&mut self) -> Option<LineError> {
+        match *self.inner() {
+            Backend::MiniUart(ref mut uart) => uart.take_error(),
+            Backend::Buffered(ref mut uart) => uart.take_error(),
+            Backend::Pl011(ref mut uart) => uart.take_error(),
+        }
     }
 }
 
 impl io::Read for Console {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.inner().read(buf)
+        match *self.inner() {
+            Backend::MiniUart(ref mut uart) => io::Read::read(uart, buf),
+            Backend::Buffered(ref mut uart) => io::Read::read(uart, buf),
+            Backend::Pl011(ref mut uart) => io::Read::read(uart, buf),
+        }
     }
 }
 
 impl io::Write for Console {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.inner().write(buf)
+        let written = match *self.inner() {
+            Backend::MiniUart(ref mut uart) => io::Write::write(uart, buf)?,
+            Backend::Buffered(ref mut uart) => io::Write::write(uart, buf)?,
+            Backend::Pl011(ref mut uart) => io::Write::write(uart, buf)?,
+        };
+        self.mirror(&buf[..written]);
+        Ok(written)
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        Ok(())
+        match *self.inner() {
+            Backend::Buffered(ref mut uart) => io::Write::flush(uart),
+            _ => Ok(()),
+        }
     }
 }
 
 impl fmt::Write for Console {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        self.inner().write_str(s)
+        match *self.inner() {
+            Backend::MiniUart(ref mut uart) => fmt::Write::write_str(uart, s)?,
+            Backend::Buffered(ref mut uart) => fmt::Write::write_str(uart, s)?,
+            Backend::Pl011(ref mut uart) => fmt::Write::write_str(uart, s)?,
+        }
+        self.mirror(s.as_bytes());
+        Ok(())
     }
 }
 
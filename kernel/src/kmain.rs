@@ -16,16 +16,41 @@ pub mod lang_items;
 pub mod mutex;
 pub mod console;
 pub mod shell;
+pub mod font;
+pub mod terminal;
+pub mod traps;
 
 use pi::gpio;
+use pi::gpio::Signal;
+use pi::gpio::pinmux::{self, Assignment, Usage};
+use pi::Peripherals;
+use pi::mailbox::tags;
+use pi::generic_timer::PhysicalTimer;
+use std::time::Duration;
+use console::{kprintln, CONSOLE};
+use terminal::Terminal;
+
+/// The baud rate of the console, unless another one is detected at boot.
+const CONSOLE_BAUD: u32 = 921_600;
+
+/// How long to wait at boot, in milliseconds, for a carriage return from which
+/// to detect the console's baud rate. `None` skips detection.
+const AUTOBAUD_TIMEOUT_MS: Option<u64> = Some(500);
+
+/// The size, in pixels, and depth, in bits per pixel, of the screen the
+/// console is mirrored to.
+const SCREEN_WIDTH: u32 = 640;
+const SCREEN_HEIGHT: u32 = 480;
+const SCREEN_DEPTH: u32 = 32;
 
 struct LED {
     pin: gpio::Gpio<gpio::Output>,
+    timer: PhysicalTimer,
 }
 
 impl LED {
-    fn new(pin: u8) -> LED {
-        LED { pin: gpio::Gpio::new(pin).into_output() }
+    fn new(pin: gpio::Gpio<gpio::Uninitialized>, timer: PhysicalTimer) -> LED {
+        LED { pin: pin.into_output(), timer: timer }
     }
 
     fn on(&mut self) {
@@ -38,18 +63,69 @@ impl LED {
 
     fn blink_for(&mut self, duration: u64) {
         self.on();
-        pi::timer::spin_sleep_ms(duration);
+        self.timer.sleep(Duration::from_millis(duration));
         self.off();
-        pi::timer::spin_sleep_ms(duration);
+        self.timer.sleep(Duration::from_millis(duration));
     }
 }
 
 #[no_mangle]
 pub extern "C" fn kmain() {
-    let mut led = LED::new(16);
-    for _ in 0..3 {
-        led.blink_for(300);
+    let Peripherals { mut pins, uart, mut mailbox, display, .. } = Peripherals::take().unwrap();
+    pi::timer::register_std_clock();
+    let (tx, rx) = (pins.take(14).unwrap(), pins.take(15).unwrap());
+    let status = pins.take(16).unwrap();
+
+    // Check what the pins are used for before any of them is configured.
+    let board = [
+        Assignment { pin: tx.pin(), usage: Usage::Signal(Signal::Uart1Tx), owner: "console" },
+        Assignment { pin: rx.pin(), usage: Usage::Signal(Signal::Uart1Rx), owner: "console" },
+        Assignment { pin: status.pin(), usage: Usage::Output, owner: "status LED" },
+    ];
+    let board_check = pinmux::check(&board);
+
+    let core_clock = mailbox.query(&tags::GetClockRate(tags::Clock::Core))
+        .unwrap_or(pi::uart::DEFAULT_CORE_CLOCK);
+    let mut console = uart.builder(tx, rx).baud(CONSOLE_BAUD).core_clock(core_clock);
+    // Follow the operator's terminal if they press enter in time.
+    if let Some(timeout) = AUTOBAUD_TIMEOUT_MS {
+        console = console.autobaud(b'\r', Some(Duration::from_millis(timeout)));
     }
+
+    let detected = match console.detected_baud() {
+        Some(Ok(_)) => true,
+        _ => false,
+    };
+    let baud = console.actual_baud().expect("invalid console baud rate");
+    let uart = console.build().expect("invalid console baud rate");
+    // Interrupts are serviced from here on, so output no longer stalls the
+    // kernel and input is not lost while it is busy.
+    traps::enable_irqs();
+    CONSOLE.lock().initialize(uart.into_buffered());
+    kprintln!("console: {} baud{}", baud, if detected { " (detected)" } else { "" });
+
+    match display.into_framebuffer(&mut mailbox, SCREEN_WIDTH, SCREEN_HEIGHT, SCREEN_DEPTH) {
+        Ok(framebuffer) => {
+            let terminal = Terminal::new(framebuffer);
+            let (columns, rows) = (terminal.columns(), terminal.rows());
+            CONSOLE.lock().attach_screen(terminal);
+            kprintln!("console: mirrored to a {}x{} screen", columns, rows);
+        }
+        Err(e) => kprintln!("console: no screen ({:?})", e),
+    }
+
+    // The console is brought up regardless, to report a bad assignment.
+    match board_check {
+        Ok(()) => {
+            let timer = unsafe { PhysicalTimer::new() };
+            let mut led = LED::new(status, timer);
+            for _ in 0..3 {
+                led.blink_for(300);
+            }
+        }
+        Err(e) => kprintln!("error: invalid board pin assignment: {:?}", e),
+    }
+
     shell::shell("> ");
     // for _ in 0.. {
     //     led.blink_for(500);
@@ -94,6 +94,11 @@ pub fn shell(prefix: &str) -> ! {
                     }
                 }
             }
+
+            if let Some(error) = console.take_error() {
+                // bytes were dropped, so the line may not be what was typed
+                write!(console, "\nwarning: input lost ({:?})", error).unwrap();
+            }
         }
         kprintln!();
 
@@ -0,0 +1,518 @@
+use std::{char, fmt, io};
+
+use pi::framebuffer::{Color, Framebuffer};
+
+use font;
+
+/// The maximum number of parameters kept for a control sequence. Further
+/// parameters are ignored.
+const MAX_PARAMS: usize = 8;
+
+/// The distance between tab stops, in columns.
+const TAB_WIDTH: u32 = 8;
+
+/// The default foreground color, as an index into `PALETTE`.
+const DEFAULT_FG: u8 = 7;
+
+/// The default background color, as an index into `PALETTE`.
+const DEFAULT_BG: u8 = 0;
+
+/// The colors selected by SGR parameters 30-37 and 40-47, followed by their
+/// bright variants, selected by 90-97 and 100-107 or by bold text.
+const PALETTE: [Color; 16] = [
+    Color::rgb(0x00, 0x00, 0x00), // black
+    Color::rgb(0xAA, 0x00, 0x00), // red
+    Color::rgb(0x00, 0xAA, 0x00), // green
+    Color::rgb(0xAA, 0x55, 0x00), // yellow
+    Color::rgb(0x00, 0x00, 0xAA), // blue
+    Color::rgb(0xAA, 0x00, 0xAA), // magenta
+    Color::rgb(0x00, 0xAA, 0xAA), // cyan
+    Color::rgb(0xAA, 0xAA, 0xAA), // white
+    Color::rgb(0x55, 0x55, 0x55), // bright black
+    Color::rgb(0xFF, 0x55, 0x55), // bright red
+    Color::rgb(0x55, 0xFF, 0x55), // bright green
+    Color::rgb(0xFF, 0xFF, 0x55), // bright yellow
+    Color::rgb(0x55, 0x55, 0xFF), // bright blue
+    Color::rgb(0xFF, 0x55, 0xFF), // bright magenta
+    Color::rgb(0x55, 0xFF, 0xFF), // bright cyan
+    Color::rgb(0xFF, 0xFF, 0xFF), // bright white
+];
+
+/// Where the terminal is in parsing an escape sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Printing characters and handling control characters.
+    Normal,
+    /// After an `ESC`.
+    Escape,
+    /// After an `ESC [`, reading a control sequence.
+    Csi,
+}
+
+/// A text terminal drawn on a framebuffer.
+///
+/// The screen is divided into cells of `font::WIDTH` by `font::HEIGHT`
+/// pixels. Text is written as UTF-8; characters missing from the font are
+/// drawn as a hollow box. Writing past the last column wraps to the next line
+/// and writing past the last line scrolls the screen up.
+///
+/// The following control characters are handled: `\n` (which also returns to
+/// the first column), `\r`, backspace and tab. Others, such as the bell, are
+/// ignored. The following escape sequences are handled, where `n` and `m` are
+/// optional decimal parameters:
+///
+///   * `ESC [ n A`, `B`, `C`, `D`: move the cursor `n` cells up, down, right
+///     or left.
+///   * `ESC [ n ; m H` or `f`: move the cursor to line `n` and column `m`,
+///     counting from `1`.
+///   * `ESC [ n J`: clear to the end of the screen (`0`), to its start (`1`)
+///     or the whole screen (`2`).
+///   * `ESC [ n K`: clear to the end of the line (`0`), to its start (`1`) or
+///     the whole line (`2`).
+///   * `ESC [ ... m`: set the text attributes: reset (`0`), bold (`1`, drawn
+///     with the bright colors), normal (`22`), reverse (`7`, `27`),
+///     foreground (`30`-`37`, `90`-`97`, default `39`) and background
+///     (`40`-`47`, `100`-`107`, default `49`).
+///   * `ESC [ ? 25 h` and `l`: show and hide the cursor.
+///   * `ESC [ s` and `u`, or `ESC 7` and `8`: save and restore the cursor.
+///   * `ESC c`: reset the terminal and clear the screen.
+///
+/// Other escape sequences are consumed and ignored.
+pub struct Terminal {
+    framebuffer: Framebuffer,
+    columns: u32,
+    rows: u32,
+    /// The cursor's column. Equal to `columns` after writing to the last
+    /// column, until the next character wraps the line.
+    column: u32,
+    row: u32,
+    saved: (u32, u32),
+    fg: u8,
+    bg: u8,
+    bold: bool,
+    reverse: bool,
+    cursor_visible: bool,
+    cursor_drawn: bool,
+    state: State,
+    params: [u32; MAX_PARAMS],
+    /// The number of parameters of the current control sequence so far.
+    nparams: usize,
+    private: bool,
+    /// The bits of the UTF-8 sequence being decoded and how many continuation
+    /// bytes are still expected.
+    utf8: (u32, u8),
+}
+
+impl Terminal {
+    /// Returns a terminal covering `framebuffer` and clears the screen.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `framebuffer` is smaller than a single character cell.
+    pub fn new(framebuffer: Framebuffer) -> Terminal {
+        let columns = framebuffer.width() / font::WIDTH;
+        let rows = framebuffer.height() / font::HEIGHT;
+        if columns == 0 || rows == 0 {
+            panic!("Terminal::new(): framebuffer is smaller than a character cell");
+        }
+
+        let mut terminal = Terminal {
+            framebuffer: framebuffer,
+            columns: columns,
+            rows: rows,
+            column: 0,
+            row: 0,
+            saved: (0, 0),
+            fg: DEFAULT_FG,
+            bg: DEFAULT_BG,
+            bold: false,
+            reverse: false,
+            cursor_visible: true,
+            cursor_drawn: false,
+            state: State::Normal,
+            params: [0; MAX_PARAMS],
+            nparams: 0,
+            private: false,
+            utf8: (0, 0),
+        };
+
+        terminal.reset();
+        terminal.show_cursor();
+        terminal
+    }
+
+    /// Returns the number of columns of text.
+    pub fn columns(&self) -> u32 {
+        self.columns
+    }
+
+    /// Returns the number of rows of text.
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    /// Returns the cursor's position as `(column, row)`, counting from `0`.
+    pub fn cursor(&self) -> (u32, u32) {
+        (self.column.min(self.columns - 1), self.row)
+    }
+
+    /// Writes the byte `byte`, which may be part of a UTF-8 character or an
+    /// escape sequence.
+    pub fn write_byte(&mut self, byte: u8) {
+        self.write_bytes(&[byte]);
+    }
+
+    /// Writes `bytes`, which may start or end in the middle of a UTF-8
+    /// character or an escape sequence.
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.hide_cursor();
+        for &byte in bytes {
+            self.feed(byte);
+        }
+        self.show_cursor();
+    }
+
+    /// Decodes `byte` as part of UTF-8 text.
+    fn feed(&mut self, byte: u8) {
+        let (code, remaining) = self.utf8;
+        if byte & 0xC0 == 0x80 {
+            // a continuation byte
+            if remaining == 0 {
+                self.print(char::REPLACEMENT_CHARACTER);
+            } else if remaining == 1 {
+                self.utf8 = (0, 0);
+                let code = code << 6 | (byte & 0x3F) as u32;
+                self.print(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+            } else {
+                self.utf8 = (code << 6 | (byte & 0x3F) as u32, remaining - 1);
+            }
+            return;
+        }
+
+        if remaining > 0 {
+            // the previous character was cut short
+            self.utf8 = (0, 0);
+            self.print(char::REPLACEMENT_CHARACTER);
+        }
+
+        if byte >= 0xF8 {
+            self.print(char::REPLACEMENT_CHARACTER);
+        } else if byte >= 0xF0 {
+            self.utf8 = ((byte & 0x07) as u32, 3);
+        } else if byte >= 0xE0 {
+            self.utf8 = ((byte & 0x0F) as u32, 2);
+        } else if byte >= 0xC0 {
+            self.utf8 = ((byte & 0x1F) as u32, 1);
+        } else {
+            self.ascii(byte);
+        }
+    }
+
+    /// Handles the ASCII character `byte` according to the parser's state.
+    fn ascii(&mut self, byte: u8) {
+        match byte {
+            b'\x1B' => self.state = State::Escape,
+            // CAN and SUB abort an escape sequence
+            b'\x18' | b'\x1A' => self.state = State::Normal,
+            _ => match self.state {
+                State::Normal => self.control_or_print(byte),
+                State::Escape => self.escape(byte),
+                State::Csi => self.csi(byte),
+            }
+        }
+    }
+
+    /// Handles `byte` outside of an escape sequence.
+    fn control_or_print(&mut self, byte: u8) {
+        match byte {
+            b'\n' => self.newline(),
+            b'\r' => self.column = 0,
+            b'\x08' => self.column = self.column.saturating_sub(1),
+            b'\t' => {
+                let next = (self.column / TAB_WIDTH + 1) * TAB_WIDTH;
+                self.column = next.min(self.columns - 1);
+            }
+            _ if byte < 0x20 || byte == 0x7F => {  }
+            _ => self.print(byte as char)
+        }
+    }
+
+    /// Handles `byte` following an `ESC`.
+    fn escape(&mut self, byte: u8) {
+        self.state = State::Normal;
+        match byte {
+            b'[' => {
+                self.state = State::Csi;
+                self.params = [0; MAX_PARAMS];
+                self.nparams = 1;
+                self.private = false;
+            }
+            b'7' => self.save_cursor(),
+            b'8' => self.restore_cursor(),
+            b'c' => self.reset(),
+            _ => {  }
+        }
+    }
+
+    /// Handles `byte` in a control sequence.
+    fn csi(&mut self, byte: u8) {
+        if byte >= b'0' && byte <= b'9' {
+            let i = self.nparams - 1;
+            if i < MAX_PARAMS {
+                let digit = (byte - b'0') as u32;
+                self.params[i] = self.params[i].saturating_mul(10).saturating_add(digit);
+            }
+        } else if byte == b';' {
+            self.nparams = self.nparams.saturating_add(1);
+        } else if byte == b'?' {
+            self.private = true;
+        } else if byte >= 0x40 && byte <= 0x7E {
+            self.state = State::Normal;
+            self.execute(byte);
+        }
+    }
+
+    /// Returns parameter `i` of the control sequence, or `default` if it is
+    /// missing or `0`.
+    fn param(&self, i: usize, default: u32) -> u32 {
+        match self.params.get(i) {
+            Some(&param) if i < self.nparams && param != 0 => param,
+            _ => default
+        }
+    }
+
+    /// Executes the control sequence ending in `byte`.
+    fn execute(&mut self, byte: u8) {
+        let column = self.column.min(self.columns - 1);
+        match byte {
+            b'A' => self.row = self.row.saturating_sub(self.param(0, 1)),
+            b'B' => self.row = self.row.saturating_add(self.param(0, 1)).min(self.rows - 1),
+            b'C' => {
+                self.column = column.saturating_add(self.param(0, 1)).min(self.columns - 1)
+            }
+            b'D' => self.column = column.saturating_sub(self.param(0, 1)),
+            b'H' | b'f' => {
+                self.row = (self.param(0, 1) - 1).min(self.rows - 1);
+                self.column = (self.param(1, 1) - 1).min(self.columns - 1);
+            }
+            b'J' => {
+                let mode = self.param(0, 0);
+                self.erase_display(mode);
+            }
+            b'K' => {
+                let mode = self.param(0, 0);
+                self.erase_line(mode);
+            }
+            b'm' => self.select_graphic_rendition(),
+            b'h' | b'l' if self.private => {
+                if self.param(0, 0) == 25 {
+                    self.cursor_visible = byte == b'h';
+                }
+            }
+            b's' => self.save_cursor(),
+            b'u' => self.restore_cursor(),
+            _ => {  }
+        }
+    }
+
+    /// Sets the text attributes from the parameters of an `m` sequence.
+    fn select_graphic_rendition(&mut self) {
+        for i in 0..self.nparams.min(MAX_PARAMS) {
+            let param = self.params[i];
+            if param == 0 {
+                self.fg = DEFAULT_FG;
+                self.bg = DEFAULT_BG;
+                self.bold = false;
+                self.reverse = false;
+            } else if param == 1 {
+                self.bold = true;
+            } else if param == 22 {
+                self.bold = false;
+            } else if param == 7 {
+                self.reverse = true;
+            } else if param == 27 {
+                self.reverse = false;
+            } else if param >= 30 && param <= 37 {
+                self.fg = (param - 30) as u8;
+            } else if param == 39 {
+                self.fg = DEFAULT_FG;
+            } else if param >= 40 && param <= 47 {
+                self.bg = (param - 40) as u8;
+            } else if param == 49 {
+                self.bg = DEFAULT_BG;
+            } else if param >= 90 && param <= 97 {
+                self.fg = (param - 90) as u8 + 8;
+            } else if param >= 100 && param <= 107 {
+                self.bg = (param - 100) as u8 + 8;
+            }
+        }
+    }
+
+    /// Returns the foreground and background colors of new text.
+    fn colors(&self) -> (Color, Color) {
+        let fg = if self.bold && self.fg < 8 { self.fg + 8 } else { self.fg };
+        let (fg, bg) = (PALETTE[fg as usize], PALETTE[self.bg as usize]);
+        if self.reverse { (bg, fg) } else { (fg, bg) }
+    }
+
+    /// Resets the text attributes and cursor, and clears the screen.
+    fn reset(&mut self) {
+        self.fg = DEFAULT_FG;
+        self.bg = DEFAULT_BG;
+        self.bold = false;
+        self.reverse = false;
+        self.cursor_visible = true;
+        self.column = 0;
+        self.row = 0;
+        self.saved = (0, 0);
+        self.erase_display(2);
+    }
+
+    fn save_cursor(&mut self) {
+        self.saved = (self.column, self.row);
+    }
+
+    fn restore_cursor(&mut self) {
+        let (column, row) = self.saved;
+        self.column = column;
+        self.row = row;
+    }
+
+    /// Draws `c` at the cursor and advances it, wrapping to the next line
+    /// first if the cursor is past the last column.
+    fn print(&mut self, c: char) {
+        if self.column >= self.columns {
+            self.newline();
+        }
+
+        let (fg, bg) = self.colors();
+        let (x, y) = (self.column * font::WIDTH, self.row * font::HEIGHT);
+        for (dy, &bits) in font::glyph(c).iter().enumerate() {
+            for dx in 0..font::WIDTH {
+                let color = if (bits >> dx) & 1 == 1 { fg } else { bg };
+                self.framebuffer.set_pixel(x + dx, y + dy as u32, color);
+            }
+        }
+
+        self.column += 1;
+    }
+
+    /// Moves the cursor to the start of the next line, scrolling the screen up
+    /// if it is on the last line.
+    fn newline(&mut self) {
+        self.column = 0;
+        if self.row + 1 < self.rows {
+            self.row += 1;
+            return;
+        }
+
+        let bg = self.colors().1;
+        self.framebuffer.scroll_up(font::HEIGHT, bg);
+        // The framebuffer may be taller than the rows of text.
+        let (row, columns) = (self.row, self.columns);
+        self.erase(row, 0, columns);
+    }
+
+    /// Clears the cells of row `row` from column `start` up to, but not
+    /// including, column `end`.
+    fn erase(&mut self, row: u32, start: u32, end: u32) {
+        let bg = self.colors().1;
+        self.framebuffer.fill_rect(start * font::WIDTH, row * font::HEIGHT,
+                                   (end - start) * font::WIDTH, font::HEIGHT, bg);
+    }
+
+    /// Clears rows `start` up to, but not including, `end`.
+    fn erase_rows(&mut self, start: u32, end: u32) {
+        let bg = self.colors().1;
+        let width = self.columns * font::WIDTH;
+        self.framebuffer.fill_rect(0, start * font::HEIGHT, width,
+                                   (end - start) * font::HEIGHT, bg);
+    }
+
+    /// Clears part of the screen as selected by `mode`, the parameter of a `J`
+    /// sequence.
+    fn erase_display(&mut self, mode: u32) {
+        let (row, rows) = (self.row, self.rows);
+        match mode {
+            0 => {
+                self.erase_line(0);
+                self.erase_rows(row + 1, rows);
+            }
+            1 => {
+                self.erase_rows(0, row);
+                self.erase_line(1);
+            }
+            2 | 3 => {
+                let bg = self.colors().1;
+                self.framebuffer.clear(bg);
+            }
+            _ => {  }
+        }
+    }
+
+    /// Clears part of the cursor's line as selected by `mode`, the parameter
+    /// of a `K` sequence.
+    fn erase_line(&mut self, mode: u32) {
+        let (row, column, columns) = (self.row, self.column, self.columns);
+        match mode {
+            0 => self.erase(row, column.min(columns), columns),
+            1 => self.erase(row, 0, (column + 1).min(columns)),
+            2 => self.erase(row, 0, columns),
+            _ => {  }
+        }
+    }
+
+    /// Inverts the colors of the cell under the cursor.
+    fn toggle_cursor(&mut self) {
+        let (column, row) = self.cursor();
+        let bytes = self.framebuffer.depth() as usize / 8;
+        let pitch = self.framebuffer.pitch() as usize;
+        let x = (column * font::WIDTH) as usize;
+        let y = (row * font::HEIGHT) as usize;
+        let buffer = self.framebuffer.buffer();
+        for dy in 0..font::HEIGHT as usize {
+            let line = (y + dy) * pitch;
+            for dx in 0..font::WIDTH as usize {
+                let pixel = line + (x + dx) * bytes;
+                // Leave the padding byte of 32-bit pixels alone.
+                for byte in buffer[pixel..pixel + bytes.min(3)].iter_mut() {
+                    *byte = !*byte;
+                }
+            }
+        }
+    }
+
+    /// Draws the cursor if it is visible and not drawn.
+    fn show_cursor(&mut self) {
+        if self.cursor_visible && !self.cursor_drawn {
+            self.toggle_cursor();
+            self.cursor_drawn = true;
+        }
+    }
+
+    /// Erases the cursor if it is drawn.
+    fn hide_cursor(&mut self) {
+        if self.cursor_drawn {
+            self.toggle_cursor();
+            self.cursor_drawn = false;
+        }
+    }
+}
+
+impl io::Write for Terminal {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_bytes(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl fmt::Write for Terminal {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_bytes(s.as_bytes());
+        Ok(())
+    }
+}
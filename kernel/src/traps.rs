@@ -0,0 +1,54 @@
+use pi::generic_timer;
+use pi::uart::buffered;
+
+/// The kind of an exception.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Synchronous = 0,
+    Irq = 1,
+    Fiq = 2,
+    SError = 3,
+}
+
+/// Unmasks IRQs on the current core.
+pub fn enable_irqs() {
+    unsafe { asm!("msr DAIFClr, #2" :::: "volatile") }
+}
+
+/// Masks IRQs on the current core.
+pub fn disable_irqs() {
+    unsafe { asm!("msr DAIFSet, #2" :::: "volatile") }
+}
+
+/// Services every interrupt source the kernel handles. Each handler checks
+/// whether its interrupt is pending.
+fn handle_irq() {
+    buffered::handle_interrupt();
+    generic_timer::handle_interrupt();
+}
+
+/// Called by the exception vectors in `ext/init.S`. The kind of the exception
+/// is in the low 16 bits of `info` and the group of its vector in the next 16;
+/// `esr` is the value of `ESR_EL1`.
+///
+/// IRQs are serviced and returned from. Any other exception is a bug the
+/// kernel cannot recover from, so the core halts.
+#[no_mangle]
+pub extern "C" fn handle_exception(info: u64, _esr: u64) {
+    let kind = match info & 0xFFFF {
+        0 => Kind::Synchronous,
+        1 => Kind::Irq,
+        2 => Kind::Fiq,
+        _ => Kind::SError,
+    };
+
+    if kind == Kind::Irq {
+        handle_irq();
+        return;
+    }
+
+    // The console may be locked by the code that faulted, so don't print.
+    loop {
+        unsafe { asm!("wfe" :::: "volatile") }
+    }
+}
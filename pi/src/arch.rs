@@ -0,0 +1,59 @@
+//! The instructions the drivers issue directly.
+//!
+//! With the `mock` feature, which builds the crate for the host, they do
+//! nothing instead: system registers read as `0` and writes to them are
+//! dropped.
+
+/// Reads the system register `$reg` and returns its value as a `u64`.
+#[cfg(not(feature = "mock"))]
+pub macro read_sysreg($reg:tt) {{
+    let value: u64;
+    asm!(concat!("mrs $0, ", $reg) : "=r"(value) ::: "volatile");
+    value
+}}
+
+/// Writes the `u64` `$value` to the system register `$reg`.
+#[cfg(not(feature = "mock"))]
+pub macro write_sysreg($reg:tt, $value:expr) {{
+    let value: u64 = $value;
+    asm!(concat!("msr ", $reg, ", $0") :: "r"(value) :: "volatile");
+}}
+
+/// Executes `$insn`, an instruction without register operands.
+#[cfg(not(feature = "mock"))]
+pub macro insn($insn:tt) {
+    asm!($insn :::: "volatile")
+}
+
+/// Runs `f` with IRQs masked on the current core, then restores the previous
+/// mask.
+pub fn without_irqs<R, F: FnOnce() -> R>(f: F) -> R {
+    unsafe {
+        let daif = read_sysreg!("DAIF");
+        insn!("msr DAIFSet, #2");
+        let result = f();
+        write_sysreg!("DAIF", daif);
+        result
+    }
+}
+
+#[cfg(feature = "mock")]
+pub macro read_sysreg($reg:tt) {
+    ::arch::host($reg, 0)
+}
+
+#[cfg(feature = "mock")]
+pub macro write_sysreg($reg:tt, $value:expr) {{
+    ::arch::host($reg, $value);
+}}
+
+#[cfg(feature = "mock")]
+pub macro insn($insn:tt) {{
+    ::arch::host($insn, 0);
+}}
+
+/// Stands in for the instruction `_insn` on the host.
+#[cfg(feature = "mock")]
+pub unsafe fn host(_insn: &str, _value: u64) -> u64 {
+    0
+}
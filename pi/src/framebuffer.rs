@@ -0,0 +1,234 @@
+use core::{ptr, slice};
+
+use mailbox::{self, Mailbox, Message};
+use mailbox::tags::{self, PixelOrder};
+
+/// The alignment, in bytes, requested for the framebuffer.
+const ALIGNMENT: u32 = 16;
+
+/// An RGB color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const WHITE: Color = Color::rgb(0xFF, 0xFF, 0xFF);
+    pub const RED: Color = Color::rgb(0xFF, 0, 0);
+    pub const GREEN: Color = Color::rgb(0, 0xFF, 0);
+    pub const BLUE: Color = Color::rgb(0, 0, 0xFF);
+
+    /// Returns the color with components `r`, `g` and `b`.
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color { r: r, g: g, b: b }
+    }
+}
+
+/// Error type for framebuffer allocation failures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The property interface call failed.
+    Mailbox(mailbox::Error),
+    /// The firmware did not allocate a framebuffer.
+    NoBuffer,
+    /// The firmware set a depth, given here, that is not 16, 24 or 32 bits per
+    /// pixel.
+    UnsupportedDepth(u32),
+}
+
+impl From<mailbox::Error> for Error {
+    fn from(error: mailbox::Error) -> Error {
+        Error::Mailbox(error)
+    }
+}
+
+/// A framebuffer allocated by the firmware and shown on the display.
+///
+/// Pixels are addressed by `(x, y)` from the top left corner. Drawing outside
+/// the framebuffer is clipped.
+///
+/// The firmware hands out the same memory every time a framebuffer is
+/// allocated, so there is at most one `Framebuffer`. It is obtained through
+/// `Display::into_framebuffer()`.
+pub struct Framebuffer {
+    base: *mut u8,
+    size: usize,
+    width: u32,
+    height: u32,
+    pitch: u32,
+    depth: u32,
+    order: PixelOrder,
+}
+
+// The framebuffer memory is owned by the only `Framebuffer`.
+unsafe impl Send for Framebuffer {  }
+
+impl Framebuffer {
+    /// Asks the firmware for a `width` by `height` framebuffer with `depth`
+    /// bits per pixel. The firmware may choose different values; see
+    /// `width()`, `height()` and `depth()`.
+    ///
+    /// The public way to allocate the framebuffer is
+    /// `Display::into_framebuffer()`, which ensures that this is called once.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Mailbox` if the request failed, `Error::NoBuffer` if no
+    /// framebuffer was allocated, and `Error::UnsupportedDepth` if the depth
+    /// set is not one of 16, 24 or 32 bits per pixel.
+    pub(crate) fn new(mailbox: &mut Mailbox, width: u32, height: u32, depth: u32)
+        -> Result<Framebuffer, Error>
+    {
+        let mut message = Message::new();
+        let physical = message.push(&tags::SetPhysicalSize(width, height))?;
+        message.push(&tags::SetVirtualSize(width, height))?;
+        message.push(&tags::SetVirtualOffset(0, 0))?;
+        let depth = message.push(&tags::SetDepth(depth))?;
+        let order = message.push(&tags::SetPixelOrder(PixelOrder::Bgr))?;
+        let buffer = message.push(&tags::AllocateBuffer(ALIGNMENT))?;
+        let pitch = message.push(&tags::GetPitch)?;
+        mailbox.send(&mut message)?;
+
+        let (width, height) = message.get(&physical)?;
+        let (base, size) = message.get(&buffer)?;
+        if base == 0 || size == 0 {
+            return Err(Error::NoBuffer);
+        }
+
+        let depth = message.get(&depth)?;
+        match depth {
+            16 | 24 | 32 => {  }
+            _ => return Err(Error::UnsupportedDepth(depth))
+        }
+
+        Ok(Framebuffer {
+            base: mailbox::bus_to_arm(base) as *mut u8,
+            size: size as usize,
+            width: width,
+            height: height,
+            pitch: message.get(&pitch)?,
+            depth: depth,
+            order: message.get(&order)?,
+        })
+    }
+
+    /// Returns the width of the framebuffer in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the height of the framebuffer in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the number of bytes between the starts of consecutive lines.
+    pub fn pitch(&self) -> u32 {
+        self.pitch
+    }
+
+    /// Returns the number of bits per pixel.
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Returns the raw framebuffer memory.
+    pub fn buffer(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.base, self.size) }
+    }
+
+    /// Returns the number of bytes per pixel.
+    fn bytes_per_pixel(&self) -> usize {
+        self.depth as usize / 8
+    }
+
+    /// Returns `color` encoded in the framebuffer's pixel format, in the low
+    /// `bytes_per_pixel()` bytes.
+    fn encode(&self, color: Color) -> u32 {
+        // `low` is the component stored in the lowest bits.
+        let (low, high) = match self.order {
+            PixelOrder::Bgr => (color.b as u32, color.r as u32),
+            PixelOrder::Rgb => (color.r as u32, color.b as u32),
+        };
+
+        let g = color.g as u32;
+        match self.depth {
+            16 => (high >> 3) << 11 | (g >> 2) << 5 | low >> 3,
+            _ => high << 16 | g << 8 | low
+        }
+    }
+
+    /// Writes the encoded pixel `pixel` at byte offset `offset`.
+    fn write(&mut self, offset: usize, pixel: u32) {
+        let bytes = self.bytes_per_pixel();
+        let buffer = self.buffer();
+        for i in 0..bytes {
+            buffer[offset + i] = (pixel >> (8 * i)) as u8;
+        }
+    }
+
+    /// Returns the byte offset of pixel `(x, y)`, which must be in bounds.
+    fn offset(&self, x: u32, y: u32) -> usize {
+        y as usize * self.pitch as usize + x as usize * self.bytes_per_pixel()
+    }
+
+    /// Sets pixel `(x, y)` to `color`.
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: Color) {
+        if x < self.width && y < self.height {
+            let (offset, pixel) = (self.offset(x, y), self.encode(color));
+            self.write(offset, pixel);
+        }
+    }
+
+    /// Fills the `width` by `height` rectangle with its top left corner at
+    /// `(x, y)` with `color`.
+    pub fn fill_rect(&mut self, x: u32, y: u32, width: u32, height: u32, color: Color) {
+        let pixel = self.encode(color);
+        let x_end = x.saturating_add(width).min(self.width);
+        let y_end = y.saturating_add(height).min(self.height);
+        for row in y..y_end {
+            for column in x..x_end {
+                let offset = self.offset(column, row);
+                self.write(offset, pixel);
+            }
+        }
+    }
+
+    /// Fills the whole framebuffer with `color`.
+    pub fn clear(&mut self, color: Color) {
+        let (width, height) = (self.width, self.height);
+        self.fill_rect(0, 0, width, height, color);
+    }
+
+    /// Copies the image `pixels`, stored row by row and `width` pixels wide,
+    /// to the framebuffer with its top left corner at `(x, y)`.
+    pub fn blit(&mut self, x: u32, y: u32, width: u32, pixels: &[Color]) {
+        if width == 0 {
+            return;
+        }
+
+        for (i, row) in pixels.chunks(width as usize).enumerate() {
+            for (j, &color) in row.iter().enumerate() {
+                self.set_pixel(x.saturating_add(j as u32), y.saturating_add(i as u32), color);
+            }
+        }
+    }
+
+    /// Moves the contents of the framebuffer up by `rows` pixels and fills the
+    /// rows uncovered at the bottom with `color`.
+    pub fn scroll_up(&mut self, rows: u32, color: Color) {
+        let rows = rows.min(self.height);
+        let (pitch, height, width) = (self.pitch as usize, self.height, self.width);
+        let moved = (height - rows) as usize * pitch;
+        let buffer = self.buffer();
+        unsafe {
+            ptr::copy(buffer.as_ptr().offset((rows as usize * pitch) as isize),
+                      buffer.as_mut_ptr(), moved);
+        }
+
+        self.fill_rect(0, height - rows, width, rows, color);
+    }
+}
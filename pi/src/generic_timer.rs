@@ -0,0 +1,307 @@
+use core::marker::PhantomData;
+use core::time::Duration;
+
+use volatile::prelude::*;
+use volatile::{Volatile, Mmio};
+
+use arch::{read_sysreg, write_sysreg, insn};
+use timer::{self, Instant};
+
+/// The address of the first per-core timer interrupt control register of the
+/// ARM local interrupt controller (`Core0TimersIntCtrl`). The registers of
+/// the other cores follow at 4 byte intervals.
+const LOCAL_TIMER_INT_CTRL: usize = 0x4000_0040;
+
+/// The `Core*TimersIntCtrl` bit routing the physical timer's interrupt
+/// (`nCNTPNSIRQ`) to the core's IRQ line.
+const CNTPNSIRQ_IRQ: u32 = 1 << 1;
+
+/// Deadlines closer than this many microseconds are waited for by spinning,
+/// since idling the core would not pay off.
+const MIN_IDLE_US: u64 = 20;
+
+bitfields! {
+    /// The physical timer control register (`CNTP_CTL_EL0`).
+    pub struct Ctl(u64) {
+        /// Whether the timer is enabled.
+        rw enabled, with_enabled @ 0, 1: bool;
+        /// Whether the timer's interrupt is masked.
+        rw interrupt_masked, with_interrupt_masked @ 1, 1: bool;
+        /// Whether the timer condition is met.
+        r condition_met @ 2, 1: bool;
+    }
+}
+
+/// Returns the frequency of the generic timer counter in Hz (`CNTFRQ_EL0`).
+///
+/// # Panics
+///
+/// Panics if `CNTFRQ_EL0` is `0`, which means the firmware did not program
+/// it. Every conversion between ticks and time goes through this function.
+pub fn frequency() -> u64 {
+    let frequency = unsafe { read_sysreg!("CNTFRQ_EL0") & 0xFFFF_FFFF };
+    if frequency == 0 {
+        panic!("generic_timer::frequency(): CNTFRQ_EL0 was not set by the firmware");
+    }
+
+    frequency
+}
+
+/// Returns the current value of the generic timer's physical counter
+/// (`CNTPCT_EL0`). The counter is shared by all cores and counts at
+/// `frequency()` Hz.
+pub fn ticks() -> u64 {
+    unsafe {
+        // Keep the read from being performed ahead of earlier instructions.
+        insn!("isb");
+        read_sysreg!("CNTPCT_EL0")
+    }
+}
+
+/// Converts a number of counter ticks to a `Duration`.
+///
+/// # Panics
+///
+/// Panics if the counter frequency is unset. See `frequency()`.
+pub fn ticks_to_duration(ticks: u64) -> Duration {
+    let frequency = frequency();
+    let secs = ticks / frequency;
+    let nanos = (ticks % frequency) * 1_000_000_000 / frequency;
+    Duration::new(secs, nanos as u32)
+}
+
+/// Converts `duration` to a number of counter ticks, rounding down. Saturates
+/// at `u64::max_value()`.
+///
+/// # Panics
+///
+/// Panics if the counter frequency is unset. See `frequency()`.
+pub fn duration_to_ticks(duration: Duration) -> u64 {
+    let frequency = frequency();
+    let sub = duration.subsec_nanos() as u64 * frequency / 1_000_000_000;
+    duration.as_secs()
+        .checked_mul(frequency)
+        .and_then(|ticks| ticks.checked_add(sub))
+        .unwrap_or(u64::max_value())
+}
+
+/// Returns the time since the counter started, as read from the generic
+/// timer.
+pub fn now() -> Duration {
+    ticks_to_duration(ticks())
+}
+
+/// Services the physical timer interrupt of the current core if it is
+/// pending, by masking it; the timer stays expired, so `sleep_until` still
+/// sees its deadline as passed.
+///
+/// This must be called from the kernel's IRQ handler whenever IRQs may be
+/// unmasked during `PhysicalTimer::sleep_until`. Otherwise, the timer's
+/// interrupt would be taken over and over.
+pub fn handle_interrupt() {
+    let ctl = Ctl::from_bits(unsafe { read_sysreg!("CNTP_CTL_EL0") });
+    if ctl.enabled() && ctl.condition_met() && !ctl.interrupt_masked() {
+        unsafe { write_sysreg!("CNTP_CTL_EL0", ctl.with_interrupt_masked(true).bits()) }
+    }
+}
+
+/// Returns the number of the current core.
+fn core_id() -> usize {
+    unsafe { read_sysreg!("MPIDR_EL1") as usize & 0b11 }
+}
+
+/// How a sleep was spent. See `PhysicalTimer::sleep`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Slept {
+    /// The time the whole sleep took.
+    pub total: Duration,
+    /// The part of `total` the core spent idle in `wfi`. The rest was spent
+    /// spinning.
+    pub idle: Duration,
+}
+
+/// The EL1 physical timer of the current core.
+///
+/// Each core has its own physical timer, programmed through system registers,
+/// so a `PhysicalTimer` must stay on the core that created it; it is neither
+/// `Send` nor `Sync`. A timer is either disarmed, armed for a one-shot
+/// deadline, or armed for a periodic deadline. When the deadline passes, the
+/// timer's condition is met and, unless masked, its interrupt is asserted.
+pub struct PhysicalTimer {
+    period: Option<u64>,
+    _not_send: PhantomData<*const ()>
+}
+
+impl PhysicalTimer {
+    /// Returns the physical timer of the current core, disarmed and with its
+    /// interrupt masked.
+    ///
+    /// # Safety
+    ///
+    /// No other `PhysicalTimer` for the current core may be in use.
+    pub unsafe fn new() -> PhysicalTimer {
+        let mut timer = PhysicalTimer { period: None, _not_send: PhantomData };
+        timer.set_control(Ctl::default().with_interrupt_masked(true));
+        timer
+    }
+
+    fn control(&self) -> Ctl {
+        Ctl::from_bits(unsafe { read_sysreg!("CNTP_CTL_EL0") })
+    }
+
+    fn set_control(&mut self, ctl: Ctl) {
+        unsafe { write_sysreg!("CNTP_CTL_EL0", ctl.bits()) }
+    }
+
+    /// Arms the timer to fire once `delay` counter ticks from now.
+    fn arm(&mut self, delay: u64) {
+        // `CNTP_TVAL_EL0` is a signed 32-bit down-counter; longer deadlines
+        // are set through the absolute compare value instead.
+        if delay <= i32::max_value() as u64 {
+            unsafe { write_sysreg!("CNTP_TVAL_EL0", delay) }
+        } else {
+            let deadline = ticks().saturating_add(delay);
+            unsafe { write_sysreg!("CNTP_CVAL_EL0", deadline) }
+        }
+
+        let ctl = self.control().with_enabled(true);
+        self.set_control(ctl);
+    }
+
+    /// Arms the timer to fire once, `after` from now. Replaces any previous
+    /// deadline.
+    pub fn set_oneshot(&mut self, after: Duration) {
+        self.period = None;
+        self.arm(duration_to_ticks(after));
+    }
+
+    /// Arms the timer to fire every `period`, starting `period` from now.
+    /// Replaces any previous deadline. Each expiry must be acknowledged with
+    /// `acknowledge()` or `wait()`, which also schedule the next one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `period` is shorter than one counter tick.
+    pub fn set_periodic(&mut self, period: Duration) {
+        let ticks = duration_to_ticks(period);
+        if ticks == 0 {
+            panic!("PhysicalTimer::set_periodic(): period {:?} is too short", period);
+        }
+
+        self.period = Some(ticks);
+        self.arm(ticks);
+    }
+
+    /// Disarms the timer.
+    pub fn disable(&mut self) {
+        self.period = None;
+        let ctl = self.control().with_enabled(false);
+        self.set_control(ctl);
+    }
+
+    /// Masks or unmasks the timer's interrupt. The interrupt must additionally
+    /// be routed to the core by the local interrupt controller.
+    pub fn set_interrupt_masked(&mut self, masked: bool) {
+        let ctl = self.control().with_interrupt_masked(masked);
+        self.set_control(ctl);
+    }
+
+    /// Returns `true` if the timer is armed and its deadline has passed.
+    pub fn is_expired(&self) -> bool {
+        let ctl = self.control();
+        ctl.enabled() && ctl.condition_met()
+    }
+
+    /// Acknowledges an expiry. A periodic timer is advanced to its next
+    /// deadline, which is scheduled relative to the previous one so that no
+    /// drift accumulates; a one-shot timer is disarmed.
+    pub fn acknowledge(&mut self) {
+        match self.period {
+            Some(period) => unsafe {
+                let deadline = read_sysreg!("CNTP_CVAL_EL0").wrapping_add(period);
+                write_sysreg!("CNTP_CVAL_EL0", deadline);
+            },
+            None => self.disable()
+        }
+    }
+
+    /// Spins until the timer expires, then acknowledges the expiry. Returns
+    /// immediately if the timer is disarmed.
+    pub fn wait(&mut self) {
+        if !self.control().enabled() {
+            return;
+        }
+
+        while !self.is_expired() {  }
+        self.acknowledge();
+    }
+
+    /// Sleeps for at least `duration`, idling the core in between. See
+    /// `sleep_until`.
+    pub fn sleep(&mut self, duration: Duration) -> Slept {
+        self.sleep_until(timer::deadline(duration))
+    }
+
+    /// Sleeps until `deadline`, as measured by the system timer, has passed.
+    ///
+    /// The core is idled with `wfi` until the physical timer fires at the
+    /// deadline. For this, the timer's interrupt is routed to the core's IRQ
+    /// line for the duration of the sleep; the routing is restored before
+    /// returning. Deadlines too close to idle for are waited for by spinning,
+    /// as with `timer::spin_sleep_until`, which remains the fallback where
+    /// idling is not wanted.
+    ///
+    /// The IRQ mask is left as the caller set it. If IRQs are unmasked, other
+    /// interrupts are serviced during the sleep, and the kernel's IRQ handler
+    /// must call `handle_interrupt` for the timer's own interrupt. IRQs are
+    /// only masked between the last check of the deadline and `wfi`, which
+    /// still wakes on a pending IRQ, so that a wakeup cannot be missed.
+    ///
+    /// Any deadline previously armed on the timer is disarmed. Returns how
+    /// long the sleep took and how much of it the core spent in `wfi`.
+    pub fn sleep_until(&mut self, deadline: Instant) -> Slept {
+        let start = Instant::now();
+        let mut idle = Duration::new(0, 0);
+
+        let route = unsafe {
+            Mmio::<Volatile<u32>>::new(LOCAL_TIMER_INT_CTRL + 4 * core_id())
+        };
+        let routing = route.read();
+        route.write(routing | CNTPNSIRQ_IRQ);
+
+        // `wfi` also returns for other interrupts.
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+
+            let remaining = deadline - now;
+            if remaining < Duration::from_micros(MIN_IDLE_US) {
+                timer::spin_sleep_until(deadline);
+                break;
+            }
+
+            self.set_oneshot(remaining);
+            self.set_interrupt_masked(false);
+            unsafe {
+                let daif = read_sysreg!("DAIF");
+                insn!("msr DAIFSet, #2");
+                if !self.is_expired() {
+                    let before = Instant::now();
+                    insn!("wfi");
+                    idle += before.elapsed();
+                }
+
+                // A pending interrupt is taken here if the caller allows it.
+                write_sysreg!("DAIF", daif);
+            }
+        }
+
+        self.disable();
+        self.set_interrupt_masked(true);
+        route.write(routing);
+
+        Slept { total: start.elapsed(), idle: idle }
+    }
+}
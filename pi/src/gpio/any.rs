@@ -0,0 +1,199 @@
+use core::marker::PhantomData;
+
+use super::{Gpio, Function, Pull, Uninitialized, Input, Output, Alt};
+use super::pinmux;
+
+/// The mode of an `AnyPin`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// The pin has not been configured through this handle.
+    Uninitialized,
+    Input,
+    Output,
+    /// The pin is in the given alternate function, one of `Alt0` to `Alt5`.
+    Alt(Function),
+}
+
+/// Error type for invalid `AnyPin` operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The operation is not valid in the pin's current mode, given here.
+    WrongMode(Mode),
+    /// The pin cannot be put in the requested mode, given here.
+    InvalidMode(Mode),
+}
+
+/// A GPIO pin whose mode is tracked at runtime.
+///
+/// Unlike `Gpio<State>`, pins of different modes share the type `AnyPin`, so
+/// they can be kept in a table or chosen at runtime. Operations that are
+/// invalid in the pin's current mode return an `Error` instead of failing to
+/// compile.
+pub struct AnyPin {
+    gpio: Gpio<Uninitialized>,
+    mode: Mode,
+}
+
+impl AnyPin {
+    /// Returns the pin number of `self`.
+    pub fn pin(&self) -> u8 {
+        self.gpio.pin
+    }
+
+    /// Returns the current mode of the pin.
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Switches the pin to mode `mode`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidMode` if `mode` is `Uninitialized`, or if it is
+    /// `Alt(function)` and `function` is not an alternate function available
+    /// on this pin. See `pinmux::signal`.
+    pub fn set_mode(&mut self, mode: Mode) -> Result<(), Error> {
+        let function = match mode {
+            Mode::Uninitialized => return Err(Error::InvalidMode(mode)),
+            Mode::Input => Function::Input,
+            Mode::Output => Function::Output,
+            Mode::Alt(function) => match pinmux::signal(self.pin(), function) {
+                Some(_) => function,
+                None => return Err(Error::InvalidMode(mode))
+            }
+        };
+
+        self.view::<Uninitialized>().into_alt(function);
+        self.mode = mode;
+        Ok(())
+    }
+
+    /// Sets (turns on) the pin.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::WrongMode` if the pin is not in `Output` mode.
+    pub fn set(&mut self) -> Result<(), Error> {
+        self.expect(Mode::Output)?;
+        self.view::<Output>().set();
+        Ok(())
+    }
+
+    /// Clears (turns off) the pin.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::WrongMode` if the pin is not in `Output` mode.
+    pub fn clear(&mut self) -> Result<(), Error> {
+        self.expect(Mode::Output)?;
+        self.view::<Output>().clear();
+        Ok(())
+    }
+
+    /// Reads the pin's value. Returns `true` if the level is high and `false`
+    /// if the level is low.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::WrongMode` if the pin is not in `Input` mode.
+    pub fn level(&mut self) -> Result<bool, Error> {
+        self.expect(Mode::Input)?;
+        Ok(self.view::<Input>().level())
+    }
+
+    /// Configures the pin's internal pull-up/pull-down resistor. See
+    /// `Gpio::<Input>::pull`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::WrongMode` if the pin is not in `Input` mode.
+    pub fn pull(&mut self, pull: Pull) -> Result<(), Error> {
+        self.expect(Mode::Input)?;
+        self.view::<Input>().pull(pull);
+        Ok(())
+    }
+
+    /// Converts `self` into a `Gpio` in the `Input` state. Returns `self` back
+    /// if the pin is not in `Input` mode.
+    pub fn into_input(self) -> Result<Gpio<Input>, AnyPin> {
+        match self.mode {
+            Mode::Input => Ok(self.gpio.transition()),
+            _ => Err(self)
+        }
+    }
+
+    /// Converts `self` into a `Gpio` in the `Output` state. Returns `self`
+    /// back if the pin is not in `Output` mode.
+    pub fn into_output(self) -> Result<Gpio<Output>, AnyPin> {
+        match self.mode {
+            Mode::Output => Ok(self.gpio.transition()),
+            _ => Err(self)
+        }
+    }
+
+    /// Converts `self` into a `Gpio` in the `Alt` state. Returns `self` back
+    /// if the pin is not in an alternate function.
+    pub fn into_alt(self) -> Result<Gpio<Alt>, AnyPin> {
+        match self.mode {
+            Mode::Alt(_) => Ok(self.gpio.transition()),
+            _ => Err(self)
+        }
+    }
+
+    /// Converts `self` into a `Gpio` in the `Uninitialized` state, for
+    /// instance to release it. The pin keeps its current configuration.
+    pub fn into_uninitialized(self) -> Gpio<Uninitialized> {
+        self.gpio
+    }
+
+    /// Returns `Err(Error::WrongMode)` unless the pin is in mode `mode`.
+    fn expect(&self, mode: Mode) -> Result<(), Error> {
+        if self.mode != mode {
+            return Err(Error::WrongMode(self.mode));
+        }
+
+        Ok(())
+    }
+
+    /// Returns a `Gpio` for the same pin in state `S`. The caller must have
+    /// checked that the pin is in a mode matching `S`.
+    fn view<S>(&self) -> Gpio<S> {
+        Gpio {
+            pin: self.gpio.pin,
+            registers: self.gpio.registers,
+            _state: PhantomData
+        }
+    }
+}
+
+impl From<Gpio<Uninitialized>> for AnyPin {
+    fn from(gpio: Gpio<Uninitialized>) -> AnyPin {
+        AnyPin { gpio: gpio, mode: Mode::Uninitialized }
+    }
+}
+
+impl From<Gpio<Input>> for AnyPin {
+    fn from(gpio: Gpio<Input>) -> AnyPin {
+        AnyPin { gpio: gpio.transition(), mode: Mode::Input }
+    }
+}
+
+impl From<Gpio<Output>> for AnyPin {
+    fn from(gpio: Gpio<Output>) -> AnyPin {
+        AnyPin { gpio: gpio.transition(), mode: Mode::Output }
+    }
+}
+
+impl From<Gpio<Alt>> for AnyPin {
+    /// Converts `gpio` into an `AnyPin`, reading the pin's alternate function
+    /// back from the hardware.
+    fn from(gpio: Gpio<Alt>) -> AnyPin {
+        let mode = match gpio.function() {
+            Function::Input => Mode::Input,
+            Function::Output => Mode::Output,
+            function => Mode::Alt(function)
+        };
+
+        AnyPin { gpio: gpio.transition(), mode: mode }
+    }
+}
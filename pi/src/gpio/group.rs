@@ -0,0 +1,248 @@
+use core::marker::PhantomData;
+
+use volatile::prelude::*;
+use volatile::Mmio;
+
+use super::{Gpio, Registers, Input, Output, REGISTERS};
+
+/// A group of GPIO pins in state `State` that are accessed together.
+///
+/// All pins in a group belong to the same 32-pin bank, so that a whole group
+/// can be driven with a single write to `SET` or `CLR` and sampled with a
+/// single read of `LEV`. Values passed to and returned from group operations
+/// are packed: bit `i` corresponds to the `i`th pin added to the group,
+/// regardless of its pin number.
+///
+/// Dropping a group does not return its pins to `Pins`: release them with
+/// `Pins::release_group()`, or dissolve the group with `into_pins()`.
+pub struct PinGroup<State> {
+    pins: [u8; 32],
+    len: usize,
+    bank: usize,
+    registers: Mmio<Registers>,
+    _state: PhantomData<State>
+}
+
+impl<S> PinGroup<S> {
+    /// Returns a new, empty group. The bank of the group is determined by the
+    /// first pin added to it. Public groups are created by `Pins::group()`.
+    pub(crate) fn new() -> PinGroup<S> {
+        PinGroup {
+            pins: [0; 32],
+            len: 0,
+            bank: 0,
+            registers: REGISTERS,
+            _state: PhantomData
+        }
+    }
+
+    /// Adds `gpio` to the group as its next bit.
+    ///
+    /// # Errors
+    ///
+    /// Returns `gpio` back if it is in a different bank than the pins already
+    /// in the group.
+    pub fn push(&mut self, gpio: Gpio<S>) -> Result<(), Gpio<S>> {
+        let bank = gpio.pin as usize / 32;
+        if self.len > 0 && bank != self.bank {
+            return Err(gpio);
+        }
+
+        // A bank holds at most 32 pins and each is only handed out once.
+        self.bank = bank;
+        self.pins[self.len] = gpio.pin;
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Adds `gpio` to the group as its next bit and returns the group.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `gpio` is in a different bank than the pins already in the
+    /// group.
+    pub fn with(mut self, gpio: Gpio<S>) -> PinGroup<S> {
+        if let Err(gpio) = self.push(gpio) {
+            panic!("PinGroup::with(): pin {} is not in bank {}", gpio.pin, self.bank);
+        }
+
+        self
+    }
+
+    /// Returns the number of pins in the group.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the group has no pins.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the pin numbers in the group, in bit order.
+    pub fn pins(&self) -> &[u8] {
+        &self.pins[..self.len]
+    }
+
+    /// Returns the bank-wide mask of the pins selected by the packed `value`.
+    /// Bits of `value` beyond the size of the group are ignored.
+    fn bank_mask(&self, value: u32) -> u32 {
+        let mut mask = 0;
+        for (i, &pin) in self.pins().iter().enumerate() {
+            if (value >> i) % 2 == 1 {
+                mask |= 0b1 << (pin as usize - self.bank * 32);
+            }
+        }
+
+        mask
+    }
+
+    /// Packs the bits of the bank-wide `mask` belonging to the group.
+    fn pack(&self, mask: u32) -> u32 {
+        let mut value = 0;
+        for (i, &pin) in self.pins().iter().enumerate() {
+            value |= ((mask >> (pin as usize - self.bank * 32)) & 0b1) << i;
+        }
+
+        value
+    }
+
+    /// Returns the packed mask with a bit set for every pin in the group.
+    fn all(&self) -> u32 {
+        match self.len {
+            32 => !0,
+            len => (0b1 << len) - 1
+        }
+    }
+
+    /// Dissolves the group, returning its pins in bit order.
+    pub fn into_pins(self) -> IntoPins<S> {
+        IntoPins { group: self, next: 0 }
+    }
+}
+
+impl PinGroup<Output> {
+    /// Sets (turns on) the pins selected by the packed `value` with a single
+    /// write to `SET`. Other pins are left unchanged.
+    pub fn set(&mut self, value: u32) {
+        let mask = self.bank_mask(value);
+        self.registers.SET[self.bank].write(mask);
+    }
+
+    /// Clears (turns off) the pins selected by the packed `value` with a
+    /// single write to `CLR`. Other pins are left unchanged.
+    pub fn clear(&mut self, value: u32) {
+        let mask = self.bank_mask(value);
+        self.registers.CLR[self.bank].write(mask);
+    }
+
+    /// Drives every pin in the group to the corresponding bit of `value`.
+    ///
+    /// This takes one write to `CLR` followed by one write to `SET`; pins
+    /// going low change before pins going high. Pins are never driven to a
+    /// value other than their old or new one.
+    pub fn write(&mut self, value: u32) {
+        let all = self.all();
+        self.clear(!value & all);
+        self.set(value & all);
+    }
+
+    /// Returns the packed levels the pins in the group are currently driven
+    /// to, sampled with a single read of `LEV`.
+    pub fn is_set(&self) -> u32 {
+        self.pack(self.registers.LEV[self.bank].read())
+    }
+}
+
+impl PinGroup<Input> {
+    /// Samples the levels of all pins in the group with a single read of
+    /// `LEV` and returns them packed.
+    pub fn read(&mut self) -> u32 {
+        self.pack(self.registers.LEV[self.bank].read())
+    }
+}
+
+/// An iterator over the pins of a dissolved `PinGroup`. See
+/// `PinGroup::into_pins`.
+pub struct IntoPins<S> {
+    group: PinGroup<S>,
+    next: usize,
+}
+
+impl<S> Iterator for IntoPins<S> {
+    type Item = Gpio<S>;
+
+    fn next(&mut self) -> Option<Gpio<S>> {
+        if self.next >= self.group.len {
+            return None;
+        }
+
+        let pin = self.group.pins[self.next];
+        self.next += 1;
+        Some(Gpio {
+            pin: pin,
+            registers: self.group.registers,
+            _state: PhantomData
+        })
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use volatile::mock::{self, RegisterFile};
+    use gpio::{Gpio, Output, GPIO_BASE};
+    use super::PinGroup;
+
+    fn output(pin: u8) -> Gpio<Output> {
+        unsafe { Gpio::new(pin) }.transition()
+    }
+
+    fn group(pins: &[u8]) -> PinGroup<Output> {
+        pins.iter().fold(PinGroup::new(), |group, &pin| group.with(output(pin)))
+    }
+
+    #[test]
+    fn push_keeps_one_bank() {
+        let mut group = group(&[33, 40]);
+        assert_eq!(group.push(output(5)).map_err(|gpio| gpio.pin()), Err(5));
+        assert!(group.push(output(53)).is_ok());
+        assert_eq!(group.pins(), &[33, 40, 53]);
+    }
+
+    #[test]
+    fn bank_mask_and_pack() {
+        let bank0 = group(&[7, 2, 31]);
+        assert_eq!(bank0.bank_mask(0b001), 1 << 7);
+        assert_eq!(bank0.bank_mask(0b110), 1 << 2 | 1 << 31);
+        assert_eq!(bank0.bank_mask(0b1000), 0);
+
+        assert_eq!(bank0.pack(1 << 7 | 1 << 31), 0b101);
+        assert_eq!(bank0.pack(!(1 << 2)), 0b101);
+        assert_eq!(bank0.pack(0), 0);
+        assert_eq!(bank0.all(), 0b111);
+
+        let bank1 = group(&[32, 53]);
+        assert_eq!(bank1.bank_mask(0b11), 1 << 0 | 1 << 21);
+        assert_eq!(bank1.pack(1 << 21), 0b10);
+    }
+
+    #[test]
+    fn all_of_full_bank() {
+        let pins: Vec<u8> = (0..32).collect();
+        assert_eq!(group(&pins).all(), !0);
+    }
+
+    #[test]
+    fn write_clears_then_sets() {
+        let regs = mock::install(RegisterFile::new());
+        let mut group = group(&[34, 32, 40]);
+        group.write(0b101);
+
+        assert_eq!(regs.borrow().writes_to(GPIO_BASE + 0x2C), vec![1 << 0]);
+        assert_eq!(regs.borrow().writes_to(GPIO_BASE + 0x20), vec![1 << 2 | 1 << 8]);
+        assert!(regs.borrow().writes_to(GPIO_BASE + 0x28).is_empty());
+
+        regs.borrow_mut().set(GPIO_BASE + 0x38, 1 << 0 | 1 << 8 | 1 << 9);
+        assert_eq!(group.is_set(), 0b110);
+    }
+}
@@ -0,0 +1,394 @@
+use core::fmt;
+use core::marker::PhantomData;
+
+use common::{IO_BASE, states};
+use timer;
+use volatile::prelude::*;
+use volatile::{Volatile, WriteVolatile, ReadVolatile, W1C, Reserved, Mmio};
+
+pub mod pinmux;
+pub mod any;
+pub mod snapshot;
+pub mod group;
+pub mod pads;
+
+pub use self::pinmux::Signal;
+pub use self::any::AnyPin;
+pub use self::snapshot::Snapshot;
+pub use self::group::PinGroup;
+pub use self::pads::{Pads, PadGroup, PadConfig, Drive};
+
+field_enum! {
+    /// An alternative GPIO function.
+    #[repr(u8)]
+    pub enum Function: u32 {
+        Input = 0b000,
+        Output = 0b001,
+        Alt0 = 0b100,
+        Alt1 = 0b101,
+        Alt2 = 0b110,
+        Alt3 = 0b111,
+        Alt4 = 0b011,
+        Alt5 = 0b010
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match *self {
+            Function::Input => "Input",
+            Function::Output => "Output",
+            Function::Alt0 => "Alt0",
+            Function::Alt1 => "Alt1",
+            Function::Alt2 => "Alt2",
+            Function::Alt3 => "Alt3",
+            Function::Alt4 => "Alt4",
+            Function::Alt5 => "Alt5",
+        })
+    }
+}
+
+bitfields! {
+    /// A GPIO function select register (`GPFSELn`), holding the function of
+    /// ten consecutive pins.
+    pub struct Fsel(u32) {
+        /// The function of the `n`th pin covered by this register.
+        rw function[10], with_function @ 0, 3: Function;
+    }
+}
+
+field_enum! {
+    /// A pull-up/pull-down resistor configuration for an input pin.
+    pub enum Pull: u32 {
+        None = 0b00,
+        Down = 0b01,
+        Up = 0b10
+    }
+}
+
+bitfields! {
+    /// The GPIO pull-up/down register (`GPPUD`).
+    pub struct Pud(u32) {
+        /// The resistor configuration clocked into pins by `GPPUDCLKn`.
+        rw pull, with_pull @ 0, 2: Pull;
+    }
+}
+
+/// An event that the GPIO controller can detect on an input pin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// A rising edge, sampled synchronously with the system clock.
+    RisingEdge,
+    /// A falling edge, sampled synchronously with the system clock.
+    FallingEdge,
+    /// A high level.
+    High,
+    /// A low level.
+    Low,
+    /// A rising edge, detected asynchronously so that very short pulses are
+    /// caught.
+    AsyncRisingEdge,
+    /// A falling edge, detected asynchronously so that very short pulses are
+    /// caught.
+    AsyncFallingEdge,
+}
+
+register_block! {
+    #[allow(non_snake_case)]
+    struct Registers {
+        0x00 => FSEL: [Volatile<Fsel>; 6],
+        0x18 => __r0: Reserved<u32>,
+        0x1C => SET: [WriteVolatile<u32>; 2],
+        0x24 => __r1: Reserved<u32>,
+        0x28 => CLR: [WriteVolatile<u32>; 2],
+        0x30 => __r2: Reserved<u32>,
+        0x34 => LEV: [ReadVolatile<u32>; 2],
+        0x3C => __r3: Reserved<u32>,
+        0x40 => EDS: [W1C<u32>; 2],
+        0x48 => __r4: Reserved<u32>,
+        0x4C => REN: [Volatile<u32>; 2],
+        0x54 => __r5: Reserved<u32>,
+        0x58 => FEN: [Volatile<u32>; 2],
+        0x60 => __r6: Reserved<u32>,
+        0x64 => HEN: [Volatile<u32>; 2],
+        0x6C => __r7: Reserved<u32>,
+        0x70 => LEN: [Volatile<u32>; 2],
+        0x78 => __r8: Reserved<u32>,
+        0x7C => AREN: [Volatile<u32>; 2],
+        0x84 => __r9: Reserved<u32>,
+        0x88 => AFEN: [Volatile<u32>; 2],
+        0x90 => __r10: Reserved<u32>,
+        0x94 => PUD: Volatile<Pud>,
+        0x98 => PUDCLK: [Volatile<u32>; 2],
+    }
+}
+
+/// Possible states for a GPIO pin.
+states! {
+    Uninitialized, Input, Output, Alt
+}
+
+/// A GPIP pin in state `State`.
+///
+/// The `State` generic always corresponds to an uninstantiatable type that is
+/// use solely to mark and track the state of a given GPIO pin. A `Gpio`
+/// structure starts in the `Uninitialized` state and must be transitions into
+/// one of `Input`, `Output`, or `Alt` via the `into_input`, `into_output`, and
+/// `into_alt` methods before it can be used.
+pub struct Gpio<State> {
+    pin: u8,
+    registers: Mmio<Registers>,
+    _state: PhantomData<State>
+}
+
+/// The base address of the `GPIO` registers.
+const GPIO_BASE: usize = IO_BASE + 0x200000;
+
+/// The `GPIO` register block.
+const REGISTERS: Mmio<Registers> = unsafe { Mmio::new(GPIO_BASE) };
+
+impl<T> Gpio<T> {
+    /// Returns the pin number of `self`.
+    pub fn pin(&self) -> u8 {
+        self.pin
+    }
+
+    /// Returns the function the pin is currently in, read back from its
+    /// function select register.
+    pub fn function(&self) -> Function {
+        let nfsel = self.pin as usize / 10;
+        let n = self.pin as usize % 10;
+        // Every 3-bit pattern is a valid `Function`.
+        self.registers.FSEL[nfsel].read().function(n).unwrap()
+    }
+
+    /// Transitions `self` to state `S`, consuming `self` and returning a new
+    /// `Gpio` instance in state `S`. This method should _never_ be exposed to
+    /// the public!
+    #[inline(always)]
+    fn transition<S>(self) -> Gpio<S> {
+        Gpio {
+            pin: self.pin,
+            registers: self.registers,
+            _state: PhantomData
+        }
+    }
+}
+
+impl Gpio<Uninitialized> {
+    /// Returns a new `GPIO` structure for pin number `pin`.
+    ///
+    /// Pins should be obtained through `Peripherals::take()` instead, which
+    /// hands out each pin only once.
+    ///
+    /// # Safety
+    ///
+    /// No other `Gpio` structure for `pin` may be in use.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pin` > `53`.
+    pub unsafe fn new(pin: u8) -> Gpio<Uninitialized> {
+        if pin > 53 {
+            panic!("Gpio::new(): pin {} exceeds maximum of 53", pin);
+        }
+
+        Gpio {
+            registers: REGISTERS,
+            pin: pin,
+            _state: PhantomData
+        }
+    }
+
+    /// Enables the alternative function `function` for `self`. Consumes self
+    /// and returns a `Gpio` structure in the `Alt` state.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `function` is an alternate function that is reserved on this
+    /// pin. See `pinmux::signal`.
+    pub fn into_alt(self, function: Function) -> Gpio<Alt> {
+        match function {
+            Function::Input | Function::Output => {  }
+            _ => if pinmux::signal(self.pin, function).is_none() {
+                panic!("Gpio::into_alt(): pin {} has no {:?} function", self.pin, function);
+            }
+        }
+
+        let nfsel = self.pin as usize / 10;
+        let n = self.pin as usize % 10;
+        self.registers.FSEL[nfsel].modify(|r| r.with_function(n, function));
+        self.transition()
+    }
+
+    /// Routes `signal` to this pin by enabling the alternative function that
+    /// carries it. Consumes self and returns a `Gpio` structure in the `Alt`
+    /// state.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this pin cannot carry `signal`. See `pinmux::pins_for`.
+    pub fn into_signal(self, signal: Signal) -> Gpio<Alt> {
+        match pinmux::function_for(self.pin, signal) {
+            Some(function) => self.into_alt(function),
+            None => panic!("Gpio::into_signal(): pin {} cannot carry {:?}", self.pin, signal)
+        }
+    }
+
+    /// Sets this pin to be an _output_ pin. Consumes self and returns a `Gpio`
+    /// structure in the `Output` state.
+    pub fn into_output(self) -> Gpio<Output> {
+        self.into_alt(Function::Output).transition()
+    }
+
+    /// Sets this pin to be an _input_ pin. Consumes self and returns a `Gpio`
+    /// structure in the `Input` state.
+    pub fn into_input(self) -> Gpio<Input> {
+        self.into_alt(Function::Input).transition()
+    }
+}
+
+impl Gpio<Output> {
+    /// Sets (turns on) the pin.
+    pub fn set(&mut self) {
+        let nset = self.pin as usize / 32;
+        let n = self.pin as usize - nset * 32;
+        self.registers.SET[nset].write(0b1 << n);
+    }
+
+    /// Clears (turns off) the pin.
+    pub fn clear(&mut self) {
+        let nclr = self.pin as usize / 32;
+        let n = self.pin as usize - nclr * 32;
+        self.registers.CLR[nclr].write(0b1 << n);
+    }
+
+    /// Returns `true` if the pin is currently driven high and `false` if it is
+    /// driven low.
+    pub fn is_set(&self) -> bool {
+        let nlev = self.pin as usize / 32;
+        let n = self.pin as usize - nlev * 32;
+        (self.registers.LEV[nlev].read() >> n) % 2 == 1
+    }
+}
+
+impl Gpio<Input> {
+    /// Reads the pin's value. Returns `true` if the level is high and `false`
+    /// if the level is low.
+    pub fn level(&mut self) -> bool {
+        let nlev = self.pin as usize / 32;
+        let n = self.pin as usize - nlev * 32;
+        (self.registers.LEV[nlev].read() >> n) % 2 == 1 
+    }
+
+    /// Enables detection of `event` on this pin. Once an enabled event is
+    /// detected, it stays pending until cleared with `clear_event`.
+    pub fn enable_event(&mut self, event: Event) {
+        let nreg = self.pin as usize / 32;
+        let n = self.pin as usize - nreg * 32;
+        self.event_register(event, nreg).or_mask(0b1 << n);
+    }
+
+    /// Disables detection of `event` on this pin.
+    pub fn disable_event(&mut self, event: Event) {
+        let nreg = self.pin as usize / 32;
+        let n = self.pin as usize - nreg * 32;
+        self.event_register(event, nreg).and_mask(!(0b1 << n));
+    }
+
+    /// Returns `true` if one of the events enabled on this pin was detected
+    /// and has not been cleared yet.
+    pub fn is_event_pending(&self) -> bool {
+        let neds = self.pin as usize / 32;
+        let n = self.pin as usize - neds * 32;
+        (self.registers.EDS[neds].read() >> n) % 2 == 1
+    }
+
+    /// Clears this pin's pending event, if any. Events pending on other pins
+    /// are left untouched.
+    pub fn clear_event(&mut self) {
+        let neds = self.pin as usize / 32;
+        let n = self.pin as usize - neds * 32;
+        self.registers.EDS[neds].clear(0b1 << n);
+    }
+
+    /// Returns the event detect enable register for `event` covering bank
+    /// `nreg`.
+    fn event_register(&self, event: Event, nreg: usize) -> &Volatile<u32> {
+        match event {
+            Event::RisingEdge => &self.registers.REN[nreg],
+            Event::FallingEdge => &self.registers.FEN[nreg],
+            Event::High => &self.registers.HEN[nreg],
+            Event::Low => &self.registers.LEN[nreg],
+            Event::AsyncRisingEdge => &self.registers.AREN[nreg],
+            Event::AsyncFallingEdge => &self.registers.AFEN[nreg],
+        }
+    }
+
+    /// Configures the pin's internal pull-up/pull-down resistor. The setting
+    /// persists until it is changed again, even across resets.
+    ///
+    /// This follows the sequence on page 101 of the BCM2837 documentation:
+    /// the control signal is set up in `GPPUD`, then clocked into the pin with
+    /// `GPPUDCLKn`, waiting 150 cycles for each to settle.
+    pub fn pull(&mut self, pull: Pull) {
+        let nclk = self.pin as usize / 32;
+        let n = self.pin as usize - nclk * 32;
+
+        self.registers.PUD.write(Pud::default().with_pull(pull));
+        timer::spin_sleep_cycles(150);
+        self.registers.PUDCLK[nclk].write(0b1 << n);
+        timer::spin_sleep_cycles(150);
+        self.registers.PUD.write(Pud::default().with_pull(Pull::None));
+        self.registers.PUDCLK[nclk].write(0);
+    }
+}
+
+/// Returns the pins of bank `bank` with a pending event. Bank `0` holds pins
+/// 0 to 31 and bank `1` holds pins 32 to 53; bit `n` of the returned mask is
+/// set if pin `32 * bank + n` has a pending event.
+///
+/// # Panics
+///
+/// Panics if `bank` > `1`.
+pub fn pending_events(bank: usize) -> u32 {
+    REGISTERS.EDS[bank].read()
+}
+
+/// Clears the pending events of the pins of bank `bank` that are set in
+/// `mask`. See `pending_events` for the meaning of `bank` and `mask`.
+///
+/// # Panics
+///
+/// Panics if `bank` > `1`.
+pub fn clear_events(bank: usize, mask: u32) {
+    REGISTERS.EDS[bank].clear(mask);
+}
+
+/// Reads the state of every pin. See `Snapshot`.
+pub fn snapshot() -> Snapshot {
+    Snapshot::take()
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use volatile::mock::{self, Access, RegisterFile};
+    use super::{Gpio, GPIO_BASE};
+
+    #[test]
+    fn into_output_writes_fsel() {
+        let fsel1 = GPIO_BASE + 0x04;
+        let regs = mock::install(RegisterFile::new());
+        // Every pin in GPFSEL1 on ALT3, to check that only pin 16 changes.
+        regs.borrow_mut().set(fsel1, 0x3FFF_FFFF);
+
+        unsafe { Gpio::new(16) }.into_output();
+
+        // Pin 16 is field 6 of GPFSEL1, bits 18-20; output is 0b001.
+        let expected = 0x3FFF_FFFF & !(0b111 << 18) | 0b001 << 18;
+        assert_eq!(regs.borrow().log(), &[
+            Access::Read { addr: fsel1, size: 4, value: 0x3FFF_FFFF },
+            Access::Write { addr: fsel1, size: 4, value: expected },
+        ]);
+    }
+}
@@ -0,0 +1,157 @@
+use common::IO_BASE;
+use volatile::prelude::*;
+use volatile::{Volatile, Mmio};
+
+/// The base address of the pad control registers (`PADS_0_27`).
+const PADS_BASE: usize = IO_BASE + 0x10002C;
+
+/// The password that must accompany every write to a pad control register.
+const PASSWORD: u32 = 0x5A;
+
+/// The pad control register block.
+const REGISTERS: Mmio<Registers> = unsafe { Mmio::new(PADS_BASE) };
+
+field_enum! {
+    /// The drive strength of a pad group.
+    pub enum Drive: u32 {
+        Ma2 = 0b000,
+        Ma4 = 0b001,
+        Ma6 = 0b010,
+        Ma8 = 0b011,
+        Ma10 = 0b100,
+        Ma12 = 0b101,
+        Ma14 = 0b110,
+        Ma16 = 0b111,
+    }
+}
+
+bitfields! {
+    /// A pad control register (`PADS_x_y`).
+    pub struct Pad(u32) {
+        /// The drive strength of the pads.
+        rw drive, with_drive @ 0, 3: Drive;
+        /// Whether input hysteresis is enabled.
+        rw hysteresis, with_hysteresis @ 3, 1: bool;
+        /// Whether the slew rate is unlimited.
+        rw slew_unlimited, with_slew_unlimited @ 4, 1: bool;
+        /// The write password. Must be `0x5A` for a write to take effect.
+        w with_password @ 24, 8: u32;
+    }
+}
+
+register_block! {
+    #[allow(non_snake_case)]
+    struct Registers {
+        0x00 => PADS: [Volatile<Pad>; 3],
+    }
+}
+
+/// A group of pins sharing one pad control register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PadGroup {
+    /// Pins 0 to 27 (`PADS_0_27`).
+    Pins0To27,
+    /// Pins 28 to 45 (`PADS_28_45`).
+    Pins28To45,
+    /// Pins 46 to 53 (`PADS_46_53`).
+    Pins46To53,
+}
+
+/// The configuration of a pad group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PadConfig {
+    /// The drive strength of the pads.
+    pub drive: Drive,
+    /// Whether the output slew rate is limited.
+    pub slew_limited: bool,
+    /// Whether input hysteresis is enabled.
+    pub hysteresis: bool,
+}
+
+impl Default for PadConfig {
+    /// Returns the configuration the pads are in after reset, `0x1B`: 8 mA
+    /// drive, slew rate not limited, hysteresis enabled.
+    fn default() -> PadConfig {
+        PadConfig {
+            drive: Drive::Ma8,
+            slew_limited: false,
+            hysteresis: true,
+        }
+    }
+}
+
+impl PadGroup {
+    /// Returns the pad group pin number `pin` belongs to.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pin` > `53`.
+    pub fn of(pin: u8) -> PadGroup {
+        if pin > 53 {
+            panic!("PadGroup::of(): pin {} exceeds maximum of 53", pin);
+        }
+
+        if pin <= 27 {
+            PadGroup::Pins0To27
+        } else if pin <= 45 {
+            PadGroup::Pins28To45
+        } else {
+            PadGroup::Pins46To53
+        }
+    }
+
+    /// Reads the current configuration of the pad group.
+    pub fn config(&self) -> PadConfig {
+        let pad = REGISTERS.PADS[*self as usize].read();
+        PadConfig {
+            // Every 3-bit pattern is a valid `Drive`.
+            drive: pad.drive().unwrap(),
+            slew_limited: !pad.slew_unlimited(),
+            hysteresis: pad.hysteresis(),
+        }
+    }
+}
+
+/// The pad control registers. Handed out once by `Peripherals::take()`, since
+/// a pad group is shared by every pin in it.
+pub struct Pads {
+    registers: Mmio<Registers>
+}
+
+impl Pads {
+    /// Returns a new instance of `Pads`. The public instance is handed out by
+    /// `Peripherals::take()`.
+    pub(crate) const fn new() -> Pads {
+        Pads {
+            registers: REGISTERS,
+        }
+    }
+
+    /// Configures the pad group `group` with `config`. This affects every pin
+    /// in the group, whatever its state or owner.
+    pub fn configure(&mut self, group: PadGroup, config: PadConfig) {
+        self.registers.PADS[group as usize].write(Pad::default()
+            .with_drive(config.drive)
+            .with_slew_unlimited(!config.slew_limited)
+            .with_hysteresis(config.hysteresis)
+            .with_password(PASSWORD));
+    }
+
+    /// Sets the drive strength of the pad group `group`, leaving the rest of
+    /// its configuration unchanged.
+    pub fn set_drive(&mut self, group: PadGroup, drive: Drive) {
+        self.configure(group, PadConfig { drive: drive, ..group.config() });
+    }
+
+    /// Enables or disables slew rate limiting on the pad group `group`,
+    /// leaving the rest of its configuration unchanged.
+    pub fn set_slew_limited(&mut self, group: PadGroup, slew_limited: bool) {
+        self.configure(group, PadConfig { slew_limited: slew_limited, ..group.config() });
+    }
+
+    /// Enables or disables input hysteresis on the pad group `group`, leaving
+    /// the rest of its configuration unchanged.
+    pub fn set_hysteresis(&mut self, group: PadGroup, hysteresis: bool) {
+        self.configure(group, PadConfig { hysteresis: hysteresis, ..group.config() });
+    }
+}
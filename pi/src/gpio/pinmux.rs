@@ -0,0 +1,315 @@
+use super::Function;
+
+/// A peripheral signal that an alternate function can route to a GPIO pin.
+///
+/// The names follow the alternative function assignments on pages 102 and 103
+/// of the BCM2837 documentation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    /// Data line of I2C (BSC) master `n`.
+    Sda(u8),
+    /// Clock line of I2C (BSC) master `n`.
+    Scl(u8),
+    /// General purpose clock `n`.
+    Gpclk(u8),
+    /// PWM channel `n`.
+    Pwm(u8),
+    /// Chip enable `n` of SPI controller `bus`, as `SpiCe(bus, n)`.
+    SpiCe(u8, u8),
+    /// MISO line of SPI controller `bus`.
+    SpiMiso(u8),
+    /// MOSI line of SPI controller `bus`.
+    SpiMosi(u8),
+    /// Clock line of SPI controller `bus`.
+    SpiSclk(u8),
+    /// Transmit line of the PL011 UART.
+    Uart0Tx,
+    /// Receive line of the PL011 UART.
+    Uart0Rx,
+    /// Clear to send line of the PL011 UART.
+    Uart0Cts,
+    /// Request to send line of the PL011 UART.
+    Uart0Rts,
+    /// Transmit line of the mini UART.
+    Uart1Tx,
+    /// Receive line of the mini UART.
+    Uart1Rx,
+    /// Clear to send line of the mini UART.
+    Uart1Cts,
+    /// Request to send line of the mini UART.
+    Uart1Rts,
+    /// PCM (I2S) clock.
+    PcmClk,
+    /// PCM (I2S) frame sync.
+    PcmFs,
+    /// PCM (I2S) data in.
+    PcmDin,
+    /// PCM (I2S) data out.
+    PcmDout,
+    /// BSC slave I2C data / SPI MOSI line.
+    BscSlSdaMosi,
+    /// BSC slave I2C clock / SPI clock line.
+    BscSlSclSclk,
+    /// BSC slave SPI MISO line.
+    BscSlMiso,
+    /// BSC slave SPI chip enable.
+    BscSlCe,
+    /// Address line `n` of the secondary memory interface.
+    SmiAddr(u8),
+    /// Data line `n` of the secondary memory interface.
+    SmiData(u8),
+    /// Output enable / setup strobe of the secondary memory interface.
+    SmiOe,
+    /// Write enable / read-write strobe of the secondary memory interface.
+    SmiWe,
+    /// Clock of the SD host controller.
+    Sd0Clk,
+    /// Command line of the SD host controller.
+    Sd0Cmd,
+    /// Data line `n` of the SD host controller.
+    Sd0Dat(u8),
+    /// Clock of the EMMC controller.
+    Sd1Clk,
+    /// Command line of the EMMC controller.
+    Sd1Cmd,
+    /// Data line `n` of the EMMC controller.
+    Sd1Dat(u8),
+    /// ARM JTAG test data in.
+    ArmTdi,
+    /// ARM JTAG test data out.
+    ArmTdo,
+    /// ARM JTAG return clock.
+    ArmRtck,
+    /// ARM JTAG test mode select.
+    ArmTms,
+    /// ARM JTAG test clock.
+    ArmTck,
+    /// ARM JTAG test reset.
+    ArmTrst,
+}
+
+use self::Signal::*;
+
+/// The alternate functions of each pin, indexed by pin and then by `Alt0` to
+/// `Alt5`. `None` marks a reserved function.
+static ALT_FUNCTIONS: [[Option<Signal>; 6]; 54] = [
+    /*  0 */ [Some(Sda(0)), Some(SmiAddr(5)), None, None, None, None],
+    /*  1 */ [Some(Scl(0)), Some(SmiAddr(4)), None, None, None, None],
+    /*  2 */ [Some(Sda(1)), Some(SmiAddr(3)), None, None, None, None],
+    /*  3 */ [Some(Scl(1)), Some(SmiAddr(2)), None, None, None, None],
+    /*  4 */ [Some(Gpclk(0)), Some(SmiAddr(1)), None, None, None, Some(ArmTdi)],
+    /*  5 */ [Some(Gpclk(1)), Some(SmiAddr(0)), None, None, None, Some(ArmTdo)],
+    /*  6 */ [Some(Gpclk(2)), Some(SmiOe), None, None, None, Some(ArmRtck)],
+    /*  7 */ [Some(SpiCe(0, 1)), Some(SmiWe), None, None, None, None],
+    /*  8 */ [Some(SpiCe(0, 0)), Some(SmiData(0)), None, None, None, None],
+    /*  9 */ [Some(SpiMiso(0)), Some(SmiData(1)), None, None, None, None],
+    /* 10 */ [Some(SpiMosi(0)), Some(SmiData(2)), None, None, None, None],
+    /* 11 */ [Some(SpiSclk(0)), Some(SmiData(3)), None, None, None, None],
+    /* 12 */ [Some(Pwm(0)), Some(SmiData(4)), None, None, None, Some(ArmTms)],
+    /* 13 */ [Some(Pwm(1)), Some(SmiData(5)), None, None, None, Some(ArmTck)],
+    /* 14 */ [Some(Uart0Tx), Some(SmiData(6)), None, None, None, Some(Uart1Tx)],
+    /* 15 */ [Some(Uart0Rx), Some(SmiData(7)), None, None, None, Some(Uart1Rx)],
+    /* 16 */ [None, Some(SmiData(8)), None, Some(Uart0Cts), Some(SpiCe(1, 2)), Some(Uart1Cts)],
+    /* 17 */ [None, Some(SmiData(9)), None, Some(Uart0Rts), Some(SpiCe(1, 1)), Some(Uart1Rts)],
+    /* 18 */ [Some(PcmClk), Some(SmiData(10)), None, Some(BscSlSdaMosi), Some(SpiCe(1, 0)), Some(Pwm(0))],
+    /* 19 */ [Some(PcmFs), Some(SmiData(11)), None, Some(BscSlSclSclk), Some(SpiMiso(1)), Some(Pwm(1))],
+    /* 20 */ [Some(PcmDin), Some(SmiData(12)), None, Some(BscSlMiso), Some(SpiMosi(1)), Some(Gpclk(0))],
+    /* 21 */ [Some(PcmDout), Some(SmiData(13)), None, Some(BscSlCe), Some(SpiSclk(1)), Some(Gpclk(1))],
+    /* 22 */ [None, Some(SmiData(14)), None, Some(Sd1Clk), Some(ArmTrst), None],
+    /* 23 */ [None, Some(SmiData(15)), None, Some(Sd1Cmd), Some(ArmRtck), None],
+    /* 24 */ [None, Some(SmiData(16)), None, Some(Sd1Dat(0)), Some(ArmTdo), None],
+    /* 25 */ [None, Some(SmiData(17)), None, Some(Sd1Dat(1)), Some(ArmTck), None],
+    /* 26 */ [None, None, None, Some(Sd1Dat(2)), Some(ArmTdi), None],
+    /* 27 */ [None, None, None, Some(Sd1Dat(3)), Some(ArmTms), None],
+    /* 28 */ [Some(Sda(0)), Some(SmiAddr(5)), Some(PcmClk), None, None, None],
+    /* 29 */ [Some(Scl(0)), Some(SmiAddr(4)), Some(PcmFs), None, None, None],
+    /* 30 */ [None, Some(SmiAddr(3)), Some(PcmDin), Some(Uart0Cts), None, Some(Uart1Cts)],
+    /* 31 */ [None, Some(SmiAddr(2)), Some(PcmDout), Some(Uart0Rts), None, Some(Uart1Rts)],
+    /* 32 */ [Some(Gpclk(0)), Some(SmiAddr(1)), None, Some(Uart0Tx), None, Some(Uart1Tx)],
+    /* 33 */ [None, Some(SmiAddr(0)), None, Some(Uart0Rx), None, Some(Uart1Rx)],
+    /* 34 */ [Some(Gpclk(0)), Some(SmiOe), None, None, None, None],
+    /* 35 */ [Some(SpiCe(0, 1)), Some(SmiWe), None, None, None, None],
+    /* 36 */ [Some(SpiCe(0, 0)), Some(SmiData(0)), Some(Uart0Tx), None, None, None],
+    /* 37 */ [Some(SpiMiso(0)), Some(SmiData(1)), Some(Uart0Rx), None, None, None],
+    /* 38 */ [Some(SpiMosi(0)), Some(SmiData(2)), Some(Uart0Rts), None, None, None],
+    /* 39 */ [Some(SpiSclk(0)), Some(SmiData(3)), Some(Uart0Cts), None, None, None],
+    /* 40 */ [Some(Pwm(0)), Some(SmiData(4)), None, None, Some(SpiMiso(2)), Some(Uart1Tx)],
+    /* 41 */ [Some(Pwm(1)), Some(SmiData(5)), None, None, Some(SpiMosi(2)), Some(Uart1Rx)],
+    /* 42 */ [Some(Gpclk(1)), Some(SmiData(6)), None, None, Some(SpiSclk(2)), Some(Uart1Rts)],
+    /* 43 */ [Some(Gpclk(2)), Some(SmiData(7)), None, None, Some(SpiCe(2, 0)), Some(Uart1Cts)],
+    /* 44 */ [Some(Gpclk(1)), Some(Sda(0)), Some(Sda(1)), None, Some(SpiCe(2, 1)), None],
+    /* 45 */ [Some(Pwm(1)), Some(Scl(0)), Some(Scl(1)), None, Some(SpiCe(2, 2)), None],
+    /* 46 */ [None, None, None, None, None, None],
+    /* 47 */ [None, None, None, None, None, None],
+    /* 48 */ [Some(Sd0Clk), None, None, Some(Sd1Clk), None, None],
+    /* 49 */ [Some(Sd0Cmd), None, None, Some(Sd1Cmd), None, None],
+    /* 50 */ [Some(Sd0Dat(0)), None, None, Some(Sd1Dat(0)), None, None],
+    /* 51 */ [Some(Sd0Dat(1)), None, None, Some(Sd1Dat(1)), None, None],
+    /* 52 */ [Some(Sd0Dat(2)), None, None, Some(Sd1Dat(2)), None, None],
+    /* 53 */ [Some(Sd0Dat(3)), None, None, Some(Sd1Dat(3)), None, None],
+];
+
+/// The alternate functions in the order of the columns of `ALT_FUNCTIONS`.
+const ALTS: [Function; 6] = [
+    Function::Alt0, Function::Alt1, Function::Alt2,
+    Function::Alt3, Function::Alt4, Function::Alt5
+];
+
+/// Returns the signal that `function` routes to `pin`. Returns `None` if
+/// `pin` > `53`, if `function` is `Input` or `Output`, or if the function is
+/// reserved on `pin`.
+pub fn signal(pin: u8, function: Function) -> Option<Signal> {
+    let column = ALTS.iter().position(|&alt| alt == function)?;
+    ALT_FUNCTIONS.get(pin as usize).and_then(|alts| alts[column])
+}
+
+/// Returns the alternate function that routes `signal` to `pin`, or `None` if
+/// `pin` cannot carry `signal`.
+pub fn function_for(pin: u8, signal: Signal) -> Option<Function> {
+    let alts = ALT_FUNCTIONS.get(pin as usize)?;
+    alts.iter().position(|&s| s == Some(signal)).map(|column| ALTS[column])
+}
+
+/// Returns an iterator over the pins that can carry `signal`, yielding each
+/// pin along with the alternate function that selects `signal` on it.
+pub fn pins_for(signal: Signal) -> PinsFor {
+    PinsFor { signal, pin: 0 }
+}
+
+/// Iterator over the pins that can carry a signal. Returned by `pins_for`.
+#[derive(Debug)]
+pub struct PinsFor {
+    signal: Signal,
+    pin: u8,
+}
+
+impl Iterator for PinsFor {
+    type Item = (u8, Function);
+
+    fn next(&mut self) -> Option<(u8, Function)> {
+        while (self.pin as usize) < ALT_FUNCTIONS.len() {
+            let pin = self.pin;
+            self.pin += 1;
+            if let Some(function) = function_for(pin, self.signal) {
+                return Some((pin, function));
+            }
+        }
+
+        None
+    }
+}
+
+/// What a board uses a pin for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Usage {
+    Input,
+    Output,
+    Signal(Signal),
+}
+
+/// A board-level assignment of a pin to a usage on behalf of `owner`, the name
+/// of the driver or feature using the pin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Assignment {
+    pub pin: u8,
+    pub usage: Usage,
+    pub owner: &'static str,
+}
+
+/// Error type for `check` failures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The assignment names a pin greater than `53`.
+    InvalidPin(Assignment),
+    /// The assignment routes a signal to a pin that cannot carry it.
+    Unsupported(Assignment),
+    /// Both assignments claim the same pin.
+    Conflict(Assignment, Assignment),
+}
+
+/// Checks the board pin assignments `board`.
+///
+/// For instance, a status LED on GPIO16 conflicts with mini UART flow control
+/// on the usual pins, since GPIO16 carries `Uart1Cts` in `Alt5`; `Uart1Rts` is
+/// on GPIO17.
+///
+/// # Errors
+///
+/// Returns `Error::InvalidPin` or `Error::Unsupported` for the first
+/// assignment that names a non-existent pin or a signal its pin cannot carry.
+/// Returns `Error::Conflict` for the first two assignments that claim the same
+/// pin.
+pub fn check(board: &[Assignment]) -> Result<(), Error> {
+    for (i, assignment) in board.iter().enumerate() {
+        if assignment.pin > 53 {
+            return Err(Error::InvalidPin(*assignment));
+        }
+
+        if let Usage::Signal(signal) = assignment.usage {
+            if function_for(assignment.pin, signal).is_none() {
+                return Err(Error::Unsupported(*assignment));
+            }
+        }
+
+        if let Some(other) = board[..i].iter().find(|a| a.pin == assignment.pin) {
+            return Err(Error::Conflict(*other, *assignment));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use gpio::Function;
+    use super::*;
+
+    fn assign(pin: u8, usage: Usage, owner: &'static str) -> Assignment {
+        Assignment { pin, usage, owner }
+    }
+
+    #[test]
+    fn table_lookups() {
+        assert_eq!(signal(14, Function::Alt5), Some(Signal::Uart1Tx));
+        assert_eq!(signal(14, Function::Alt0), Some(Signal::Uart0Tx));
+        assert_eq!(signal(14, Function::Output), None);
+        assert_eq!(signal(54, Function::Alt0), None);
+
+        assert_eq!(function_for(15, Signal::Uart1Rx), Some(Function::Alt5));
+        assert_eq!(function_for(15, Signal::Uart1Tx), None);
+
+        let pins: Vec<_> = pins_for(Signal::Uart1Tx).collect();
+        assert_eq!(pins, vec![(14, Function::Alt5), (32, Function::Alt5), (40, Function::Alt5)]);
+    }
+
+    #[test]
+    fn check_accepts_valid_board() {
+        assert_eq!(check(&[]), Ok(()));
+        assert_eq!(check(&[
+            assign(14, Usage::Signal(Signal::Uart1Tx), "uart"),
+            assign(15, Usage::Signal(Signal::Uart1Rx), "uart"),
+            assign(16, Usage::Output, "led"),
+            assign(53, Usage::Input, "button"),
+        ]), Ok(()));
+    }
+
+    #[test]
+    fn check_rejects_invalid_pin() {
+        let bad = assign(54, Usage::Output, "led");
+        assert_eq!(check(&[assign(16, Usage::Output, "led"), bad]), Err(Error::InvalidPin(bad)));
+    }
+
+    #[test]
+    fn check_rejects_unsupported_signal() {
+        let bad = assign(16, Usage::Signal(Signal::Uart1Rts), "uart");
+        assert_eq!(check(&[bad]), Err(Error::Unsupported(bad)));
+    }
+
+    #[test]
+    fn check_rejects_conflict() {
+        let cts = assign(16, Usage::Signal(Signal::Uart1Cts), "uart");
+        let led = assign(16, Usage::Output, "led");
+        let board = [assign(14, Usage::Signal(Signal::Uart1Tx), "uart"), cts, led];
+        assert_eq!(check(&board), Err(Error::Conflict(cts, led)));
+    }
+}
@@ -0,0 +1,137 @@
+use core::fmt;
+
+use volatile::prelude::*;
+
+use super::{Function, Event, REGISTERS};
+
+/// All of the events that can be detected on a pin, in the order used by
+/// `EventSet`.
+const EVENTS: [Event; 6] = [
+    Event::RisingEdge, Event::FallingEdge, Event::High, Event::Low,
+    Event::AsyncRisingEdge, Event::AsyncFallingEdge
+];
+
+/// A set of GPIO events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EventSet(u8);
+
+impl EventSet {
+    /// Returns `true` if `event` is in the set.
+    pub fn contains(&self, event: Event) -> bool {
+        let n = EVENTS.iter().position(|&e| e == event).unwrap();
+        (self.0 >> n) % 2 == 1
+    }
+
+    /// Returns `true` if the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl fmt::Display for EventSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "-");
+        }
+
+        let mut separator = "";
+        for event in EVENTS.iter().filter(|&&e| self.contains(e)) {
+            write!(f, "{}{:?}", separator, event)?;
+            separator = ",";
+        }
+
+        Ok(())
+    }
+}
+
+/// The state of a pin at the time a `Snapshot` was taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PinState {
+    /// The function the pin is in.
+    pub function: Function,
+    /// The level of the pin: `true` if high, `false` if low.
+    pub level: bool,
+    /// The events whose detection is enabled on the pin.
+    pub events: EventSet,
+    /// Whether an event was detected on the pin and not yet cleared.
+    pub event_pending: bool,
+}
+
+/// The state of all 54 GPIO pins, read at one point in time.
+///
+/// The `Display` implementation prints one line per pin, suitable for
+/// diagnostics.
+pub struct Snapshot {
+    pins: [PinState; 54],
+}
+
+impl Snapshot {
+    /// Reads the state of every pin.
+    pub fn take() -> Snapshot {
+        let registers = REGISTERS;
+        let fsel: [_; 6] = [
+            registers.FSEL[0].read(), registers.FSEL[1].read(), registers.FSEL[2].read(),
+            registers.FSEL[3].read(), registers.FSEL[4].read(), registers.FSEL[5].read()
+        ];
+
+        let bank = |regs: &[::volatile::Volatile<u32>; 2]| [regs[0].read(), regs[1].read()];
+        let lev = [registers.LEV[0].read(), registers.LEV[1].read()];
+        let eds = [registers.EDS[0].read(), registers.EDS[1].read()];
+        let enables = [
+            bank(&registers.REN), bank(&registers.FEN), bank(&registers.HEN),
+            bank(&registers.LEN), bank(&registers.AREN), bank(&registers.AFEN)
+        ];
+
+        let mut pins = [PinState {
+            function: Function::Input,
+            level: false,
+            events: EventSet::default(),
+            event_pending: false,
+        }; 54];
+
+        for (pin, state) in pins.iter_mut().enumerate() {
+            let (nbank, n) = (pin / 32, pin % 32);
+            let mut events = 0;
+            for (i, enable) in enables.iter().enumerate() {
+                events |= (((enable[nbank] >> n) & 0b1) as u8) << i;
+            }
+
+            state.function = fsel[pin / 10].function(pin % 10).unwrap();
+            state.level = (lev[nbank] >> n) % 2 == 1;
+            state.events = EventSet(events);
+            state.event_pending = (eds[nbank] >> n) % 2 == 1;
+        }
+
+        Snapshot { pins }
+    }
+
+    /// Returns the state of pin number `pin`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pin` > `53`.
+    pub fn pin(&self, pin: u8) -> &PinState {
+        &self.pins[pin as usize]
+    }
+
+    /// Returns the state of every pin, indexed by pin number.
+    pub fn pins(&self) -> &[PinState] {
+        &self.pins
+    }
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "pin  function  level  events")?;
+        for (pin, state) in self.pins.iter().enumerate() {
+            writeln!(f, "{:>3}  {:<8}  {:<5}  {}{}",
+                pin,
+                state.function,
+                if state.level { "high" } else { "low" },
+                state.events,
+                if state.event_pending { " (pending)" } else { "" })?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+use common::IO_BASE;
+use volatile::prelude::*;
+use volatile::{Volatile, ReadVolatile, WriteVolatile, Mmio};
+
+/// The base address of the ARM interrupt controller registers.
+const INT_BASE: usize = IO_BASE + 0xB000 + 0x200;
+
+/// The interrupt controller register block.
+const REGISTERS: Mmio<Registers> = unsafe { Mmio::new(INT_BASE) };
+
+/// A peripheral interrupt, numbered as on page 113 of the BCM2837
+/// documentation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interrupt {
+    Timer1 = 1,
+    Timer3 = 3,
+    Usb = 9,
+    /// The auxiliary peripherals: the mini UART and the two mini SPIs.
+    Aux = 29,
+    Gpio0 = 49,
+    Gpio1 = 50,
+    Gpio2 = 51,
+    Gpio3 = 52,
+    Uart = 57,
+}
+
+register_block! {
+    #[allow(non_snake_case)]
+    struct Registers {
+        0x00 => IRQ_BASIC_PENDING: ReadVolatile<u32>,
+        0x04 => IRQ_PENDING: [ReadVolatile<u32>; 2],
+        0x0C => FIQ_CONTROL: Volatile<u32>,
+        0x10 => ENABLE_IRQS: [WriteVolatile<u32>; 2],
+        0x18 => ENABLE_BASIC_IRQS: WriteVolatile<u32>,
+        0x1C => DISABLE_IRQS: [WriteVolatile<u32>; 2],
+        0x24 => DISABLE_BASIC_IRQS: WriteVolatile<u32>,
+    }
+}
+
+/// An interrupt controller. Used to enable and disable interrupts as well as to
+/// check if an interrupt is pending.
+pub struct Controller {
+    registers: Mmio<Registers>
+}
+
+impl Controller {
+    /// Returns a new handle to the interrupt controller. The public instance is
+    /// handed out by `Peripherals::take()`.
+    pub(crate) const fn new() -> Controller {
+        Controller {
+            registers: REGISTERS,
+        }
+    }
+
+    /// Enables the interrupt `int`.
+    pub fn enable(&mut self, int: Interrupt) {
+        let n = int as usize;
+        self.registers.ENABLE_IRQS[n / 32].write(0b1 << (n % 32));
+    }
+
+    /// Disables the interrupt `int`.
+    pub fn disable(&mut self, int: Interrupt) {
+        let n = int as usize;
+        self.registers.DISABLE_IRQS[n / 32].write(0b1 << (n % 32));
+    }
+
+    /// Returns `true` if `int` is pending. Otherwise, returns `false`.
+    pub fn is_pending(&self, int: Interrupt) -> bool {
+        let n = int as usize;
+        (self.registers.IRQ_PENDING[n / 32].read() >> (n % 32)) % 2 == 1
+    }
+}
@@ -7,12 +7,25 @@
 #![feature(never_type)]
 
 #![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(all(feature = "std", not(feature = "mock")), feature(time_clock))]
 
 #[cfg(feature = "std")]
 extern crate core;
+#[macro_use]
 extern crate volatile;
 
+mod arch;
+
 pub mod timer;
+pub mod generic_timer;
 pub mod uart;
 pub mod gpio;
 pub mod common;
+pub mod wait;
+pub mod peripherals;
+pub mod interrupt;
+pub mod ring;
+pub mod mailbox;
+pub mod framebuffer;
+
+pub use peripherals::Peripherals;
@@ -0,0 +1,274 @@
+use core::marker::PhantomData;
+use core::sync::atomic::{compiler_fence, Ordering};
+use core::time::Duration;
+
+use volatile::prelude::*;
+use volatile::{Volatile, ReadVolatile, WriteVolatile, Reserved, Mmio};
+
+use arch::insn;
+use common::IO_BASE;
+use timer;
+use wait;
+
+pub mod tags;
+
+pub use self::tags::Tag;
+
+/// The base address of the mailbox registers.
+const MAILBOX_BASE: usize = IO_BASE + 0xB880;
+
+/// The mailbox register block.
+const REGISTERS: Mmio<Registers> = unsafe { Mmio::new(MAILBOX_BASE) };
+
+/// The mailbox channel of the property interface, ARM to VideoCore.
+pub const PROPERTY_CHANNEL: u8 = 8;
+
+/// The number of 32-bit words in a `Message`, including its header.
+pub const MESSAGE_WORDS: usize = 256;
+
+/// Set in a message or tag code once the firmware has processed it.
+const RESPONSE: u32 = 1 << 31;
+
+/// The message code of a message the firmware processed successfully.
+const RESPONSE_SUCCESS: u32 = RESPONSE;
+
+/// The message code of a message the firmware failed to parse.
+const RESPONSE_ERROR: u32 = RESPONSE | 1;
+
+/// How long to wait, in milliseconds, for the mailbox to accept a message and
+/// for the reply.
+const TIMEOUT_MS: u64 = 1000;
+
+/// The offset of the ARM physical address space in the VideoCore's bus address
+/// space, through the uncached alias.
+const BUS_OFFSET: u32 = 0xC000_0000;
+
+bitfields! {
+    /// A mailbox status register.
+    pub struct Status(u32) {
+        /// The mailbox cannot accept another message.
+        r full @ 31, 1: bool;
+        /// The mailbox holds no messages.
+        r empty @ 30, 1: bool;
+    }
+}
+
+register_block! {
+    #[allow(non_snake_case)]
+    struct Registers {
+        0x00 => READ: ReadVolatile<u32>,
+        0x04 => __r0: [Reserved<u32>; 3],
+        0x10 => PEEK: ReadVolatile<u32>,
+        0x14 => SENDER: ReadVolatile<u32>,
+        0x18 => STATUS: ReadVolatile<Status>,
+        0x1C => CONFIG: Volatile<u32>,
+        0x20 => WRITE: WriteVolatile<u32>,
+        0x24 => __r1: [Reserved<u32>; 5],
+        0x38 => WRITE_STATUS: ReadVolatile<Status>,
+    }
+}
+
+/// Converts the ARM physical address `addr` to the VideoCore bus address of
+/// the same memory, through the uncached alias.
+pub fn arm_to_bus(addr: usize) -> u32 {
+    (addr as u32 & !BUS_OFFSET) | BUS_OFFSET
+}
+
+/// Converts the VideoCore bus address `addr` to the ARM physical address of
+/// the same memory.
+pub fn bus_to_arm(addr: u32) -> usize {
+    (addr & !BUS_OFFSET) as usize
+}
+
+/// Error type for property interface calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The tag does not fit in the message.
+    MessageFull,
+    /// The firmware could not parse the message.
+    Rejected,
+    /// The firmware did not process the message.
+    NoResponse,
+    /// The mailbox did not accept the message, or no reply arrived, within
+    /// `TIMEOUT_MS`.
+    TimedOut,
+    /// The firmware did not process the tag with the given ID, usually
+    /// because it does not know it or rejected its request.
+    TagNotHandled(u32),
+    /// The response to the tag with the given ID does not fit in the space
+    /// reserved for it.
+    Truncated(u32),
+}
+
+/// A reference to a tag added to a `Message`, used to read its response.
+pub struct TagRef<T: Tag> {
+    offset: usize,
+    _tag: PhantomData<T>,
+}
+
+/// A property interface message: a buffer of tags sent to the firmware and
+/// filled in with the firmware's responses.
+///
+/// The buffer is 16-byte aligned, as the mailbox requires. Since the data
+/// cache is not enabled, the firmware sees the buffer as written.
+///
+/// ```rust,ignore
+/// let mut message = Message::new();
+/// let revision = message.push(&tags::GetBoardRevision)?;
+/// let core = message.push(&tags::GetClockRate(Clock::Core))?;
+/// mailbox.send(&mut message)?;
+/// let (revision, core) = (message.get(&revision)?, message.get(&core)?);
+/// ```
+#[repr(C)]
+#[repr(align(16))]
+pub struct Message {
+    words: [u32; MESSAGE_WORDS],
+    /// The number of words in use, including the header.
+    len: usize,
+}
+
+impl Message {
+    /// Returns a new message without any tags.
+    pub fn new() -> Message {
+        Message {
+            words: [0; MESSAGE_WORDS],
+            len: 2,
+        }
+    }
+
+    /// Adds `tag` to the message, reserving space for its response.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::MessageFull` if the tag does not fit.
+    pub fn push<T: Tag>(&mut self, tag: &T) -> Result<TagRef<T>, Error> {
+        let offset = self.len;
+        let end = offset + 3 + T::WORDS;
+
+        // Leave room for the end tag.
+        if end >= MESSAGE_WORDS {
+            return Err(Error::MessageFull);
+        }
+
+        self.words[offset] = T::ID;
+        self.words[offset + 1] = (T::WORDS * 4) as u32;
+        self.words[offset + 2] = 0;
+        for word in self.words[offset + 3..end].iter_mut() {
+            *word = 0;
+        }
+
+        tag.encode(&mut self.words[offset + 3..end]);
+        self.len = end;
+        Ok(TagRef { offset: offset, _tag: PhantomData })
+    }
+
+    /// Returns the firmware's response to `tag`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::TagNotHandled` if the firmware did not process the tag
+    /// and `Error::Truncated` if its response did not fit.
+    pub fn get<T: Tag>(&self, tag: &TagRef<T>) -> Result<T::Response, Error> {
+        let code = self.words[tag.offset + 2];
+        if code & RESPONSE == 0 {
+            return Err(Error::TagNotHandled(T::ID));
+        }
+
+        if (code & !RESPONSE) as usize > T::WORDS * 4 {
+            return Err(Error::Truncated(T::ID));
+        }
+
+        let values = tag.offset + 3;
+        Ok(T::decode(&self.words[values..values + T::WORDS]))
+    }
+
+    /// Terminates the message and fills in its header for sending.
+    fn finish(&mut self) {
+        self.words[self.len] = 0;
+        self.words[0] = ((self.len + 1) * 4) as u32;
+        self.words[1] = 0;
+    }
+}
+
+/// Orders memory accesses before and after it, for both the compiler and the
+/// other bus masters.
+fn barrier() {
+    compiler_fence(Ordering::SeqCst);
+    unsafe { insn!("dsb sy") }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// The mailbox between the ARM cores and the VideoCore.
+pub struct Mailbox {
+    registers: Mmio<Registers>
+}
+
+impl Mailbox {
+    /// Returns a new instance of `Mailbox`. The public instance is handed out
+    /// by `Peripherals::take()`.
+    pub(crate) const fn new() -> Mailbox {
+        Mailbox {
+            registers: REGISTERS,
+        }
+    }
+
+    /// Sends `data`, whose lowest 4 bits must be clear, on `channel` and
+    /// blocks until the reply on `channel` arrives. Returns the reply with
+    /// its channel bits cleared. Replies on other channels are discarded.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::TimedOut` if the mailbox stays full or no reply arrives
+    /// within `TIMEOUT_MS`.
+    pub fn call(&mut self, channel: u8, data: u32) -> Result<u32, Error> {
+        let channel = (channel & 0xF) as u32;
+        let deadline = Some(timer::deadline(Duration::from_millis(TIMEOUT_MS)));
+        wait::for_value(&self.registers.WRITE_STATUS, deadline, |status| !status.full())
+            .map_err(|_| Error::TimedOut)?;
+        self.registers.WRITE.write((data & !0xF) | channel);
+
+        loop {
+            wait::for_value(&self.registers.STATUS, deadline, |status| !status.empty())
+                .map_err(|_| Error::TimedOut)?;
+            let reply = self.registers.READ.read();
+            if reply & 0xF == channel {
+                return Ok(reply & !0xF);
+            }
+        }
+    }
+
+    /// Sends `message` to the firmware over the property interface and blocks
+    /// until the firmware has filled in its responses.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Rejected` if the firmware could not parse the message,
+    /// `Error::NoResponse` if it did not process it and `Error::TimedOut` if
+    /// it did not answer in time. Errors of individual tags are reported by
+    /// `Message::get`.
+    pub fn send(&mut self, message: &mut Message) -> Result<(), Error> {
+        message.finish();
+        let addr = arm_to_bus(message.words.as_ptr() as usize);
+
+        // The firmware accesses the message by DMA: complete the writes above
+        // before handing it over, and read the responses only once it is back.
+        barrier();
+        let reply = self.call(PROPERTY_CHANNEL, addr);
+        barrier();
+        reply?;
+
+        match message.words[1] {
+            RESPONSE_SUCCESS => Ok(()),
+            RESPONSE_ERROR => Err(Error::Rejected),
+            _ => Err(Error::NoResponse)
+        }
+    }
+
+    /// Sends a message with the single tag `tag` and returns its response.
+    pub fn query<T: Tag>(&mut self, tag: &T) -> Result<T::Response, Error> {
+        let mut message = Message::new();
+        let tag = message.push(tag)?;
+        self.send(&mut message)?;
+        message.get(&tag)
+    }
+}
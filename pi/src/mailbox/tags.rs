@@ -0,0 +1,421 @@
+/// A property interface tag: a request to the firmware and its response.
+///
+/// A tag's values are exchanged in a buffer of `WORDS` 32-bit words, large
+/// enough for both the request and the response.
+pub trait Tag {
+    /// The tag identifier.
+    const ID: u32;
+
+    /// The size of the tag's value buffer, in 32-bit words.
+    const WORDS: usize;
+
+    /// The decoded response.
+    type Response;
+
+    /// Writes the request values into `values`, which is zeroed and `WORDS`
+    /// words long. The default writes nothing.
+    fn encode(&self, _values: &mut [u32]) {  }
+
+    /// Decodes the response from `values`, which is `WORDS` words long.
+    fn decode(values: &[u32]) -> Self::Response;
+}
+
+/// A clock managed by the firmware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Clock {
+    Emmc = 1,
+    Uart = 2,
+    Arm = 3,
+    Core = 4,
+    V3d = 5,
+    H264 = 6,
+    Isp = 7,
+    Sdram = 8,
+    Pixel = 9,
+    Pwm = 10,
+}
+
+/// A device whose power is managed by the firmware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Device {
+    SdCard = 0,
+    Uart0 = 1,
+    Uart1 = 2,
+    UsbHcd = 3,
+    I2c0 = 4,
+    I2c1 = 5,
+    I2c2 = 6,
+    Spi = 7,
+    Ccp2tx = 8,
+}
+
+/// The power state of a device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PowerState {
+    /// Whether the device is powered on.
+    pub on: bool,
+    /// Whether the device exists.
+    pub exists: bool,
+}
+
+impl PowerState {
+    fn decode(state: u32) -> PowerState {
+        PowerState {
+            on: state & 0b01 != 0,
+            exists: state & 0b10 == 0,
+        }
+    }
+}
+
+/// A region of memory, as ARM physical addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryRegion {
+    /// The base address of the region.
+    pub base: usize,
+    /// The size of the region in bytes.
+    pub size: usize,
+}
+
+/// Declares a tag without request values whose response is a single word.
+macro word_tag($(#[$attr:meta])* $name:ident = $id:expr) {
+    $(#[$attr])*
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct $name;
+
+    impl Tag for $name {
+        const ID: u32 = $id;
+        const WORDS: usize = 1;
+        type Response = u32;
+
+        fn decode(values: &[u32]) -> u32 {
+            values[0]
+        }
+    }
+}
+
+/// Declares a tag requesting a value of clock `Clock`, answered in Hz.
+macro clock_tag($(#[$attr:meta])* $name:ident = $id:expr) {
+    $(#[$attr])*
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct $name(pub Clock);
+
+    impl Tag for $name {
+        const ID: u32 = $id;
+        const WORDS: usize = 2;
+        type Response = u32;
+
+        fn encode(&self, values: &mut [u32]) {
+            values[0] = self.0 as u32;
+        }
+
+        fn decode(values: &[u32]) -> u32 {
+            values[1]
+        }
+    }
+}
+
+/// Declares a tag requesting a temperature, answered in thousandths of a
+/// degree Celsius.
+macro temperature_tag($(#[$attr:meta])* $name:ident = $id:expr) {
+    $(#[$attr])*
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct $name;
+
+    impl Tag for $name {
+        const ID: u32 = $id;
+        const WORDS: usize = 2;
+        type Response = u32;
+
+        fn decode(values: &[u32]) -> u32 {
+            values[1]
+        }
+    }
+}
+
+/// Declares a tag requesting a memory region.
+macro memory_tag($(#[$attr:meta])* $name:ident = $id:expr) {
+    $(#[$attr])*
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct $name;
+
+    impl Tag for $name {
+        const ID: u32 = $id;
+        const WORDS: usize = 2;
+        type Response = MemoryRegion;
+
+        fn decode(values: &[u32]) -> MemoryRegion {
+            MemoryRegion { base: values[0] as usize, size: values[1] as usize }
+        }
+    }
+}
+
+word_tag!(
+    /// Requests the firmware revision.
+    GetFirmwareRevision = 0x0000_0001
+);
+
+word_tag!(
+    /// Requests the board model.
+    GetBoardModel = 0x0001_0001
+);
+
+word_tag!(
+    /// Requests the board revision code.
+    GetBoardRevision = 0x0001_0002
+);
+
+memory_tag!(
+    /// Requests the memory region assigned to the ARM cores.
+    GetArmMemory = 0x0001_0005
+);
+
+memory_tag!(
+    /// Requests the memory region assigned to the VideoCore.
+    GetVcMemory = 0x0001_0006
+);
+
+clock_tag!(
+    /// Requests the current rate of a clock. The rate is 0 if the clock does
+    /// not exist.
+    GetClockRate = 0x0003_0002
+);
+
+clock_tag!(
+    /// Requests the maximum rate of a clock.
+    GetMaxClockRate = 0x0003_0004
+);
+
+clock_tag!(
+    /// Requests the minimum rate of a clock.
+    GetMinClockRate = 0x0003_0007
+);
+
+temperature_tag!(
+    /// Requests the SoC temperature.
+    GetTemperature = 0x0003_0006
+);
+
+temperature_tag!(
+    /// Requests the SoC temperature above which the firmware throttles the
+    /// clocks.
+    GetMaxTemperature = 0x0003_000A
+);
+
+/// Requests the MAC address of the on-board network interface, in network
+/// byte order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GetMacAddress;
+
+impl Tag for GetMacAddress {
+    const ID: u32 = 0x0001_0003;
+    const WORDS: usize = 2;
+    type Response = [u8; 6];
+
+    fn decode(values: &[u32]) -> [u8; 6] {
+        let mut mac = [0; 6];
+        for (i, byte) in mac.iter_mut().enumerate() {
+            *byte = (values[i / 4] >> (8 * (i % 4))) as u8;
+        }
+
+        mac
+    }
+}
+
+/// Requests the board serial number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GetBoardSerial;
+
+impl Tag for GetBoardSerial {
+    const ID: u32 = 0x0001_0004;
+    const WORDS: usize = 2;
+    type Response = u64;
+
+    fn decode(values: &[u32]) -> u64 {
+        ((values[1] as u64) << 32) | values[0] as u64
+    }
+}
+
+/// Requests the power state of a device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GetPowerState(pub Device);
+
+impl Tag for GetPowerState {
+    const ID: u32 = 0x0002_0001;
+    const WORDS: usize = 2;
+    type Response = PowerState;
+
+    fn encode(&self, values: &mut [u32]) {
+        values[0] = self.0 as u32;
+    }
+
+    fn decode(values: &[u32]) -> PowerState {
+        PowerState::decode(values[1])
+    }
+}
+
+/// Powers a device on or off and returns its new power state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SetPowerState {
+    /// The device to power on or off.
+    pub device: Device,
+    /// Whether to power the device on.
+    pub on: bool,
+    /// Whether the firmware should wait for the device to become stable.
+    pub wait: bool,
+}
+
+impl Tag for SetPowerState {
+    const ID: u32 = 0x0002_8001;
+    const WORDS: usize = 2;
+    type Response = PowerState;
+
+    fn encode(&self, values: &mut [u32]) {
+        values[0] = self.device as u32;
+        values[1] = self.on as u32 | (self.wait as u32) << 1;
+    }
+
+    fn decode(values: &[u32]) -> PowerState {
+        PowerState::decode(values[1])
+    }
+}
+
+/// Sets the rate of a clock, in Hz, and returns the rate actually set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SetClockRate {
+    /// The clock to set the rate of.
+    pub clock: Clock,
+    /// The requested rate in Hz.
+    pub rate: u32,
+    /// Whether to keep the firmware from raising other clocks along with the
+    /// ARM clock.
+    pub skip_turbo: bool,
+}
+
+impl Tag for SetClockRate {
+    const ID: u32 = 0x0003_8002;
+    const WORDS: usize = 3;
+    type Response = u32;
+
+    fn encode(&self, values: &mut [u32]) {
+        values[0] = self.clock as u32;
+        values[1] = self.rate;
+        values[2] = self.skip_turbo as u32;
+    }
+
+    fn decode(values: &[u32]) -> u32 {
+        values[1]
+    }
+}
+
+/// The order of the color components of a framebuffer pixel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelOrder {
+    Bgr = 0,
+    Rgb = 1,
+}
+
+/// Declares a framebuffer tag setting a pair of values, such as a width and
+/// height, answered with the values actually set.
+macro size_tag($(#[$attr:meta])* $name:ident = $id:expr) {
+    $(#[$attr])*
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct $name(pub u32, pub u32);
+
+    impl Tag for $name {
+        const ID: u32 = $id;
+        const WORDS: usize = 2;
+        type Response = (u32, u32);
+
+        fn encode(&self, values: &mut [u32]) {
+            values[0] = self.0;
+            values[1] = self.1;
+        }
+
+        fn decode(values: &[u32]) -> (u32, u32) {
+            (values[0], values[1])
+        }
+    }
+}
+
+size_tag!(
+    /// Sets the width and height of the display, in pixels.
+    SetPhysicalSize = 0x0004_8003
+);
+
+size_tag!(
+    /// Sets the width and height of the framebuffer, in pixels.
+    SetVirtualSize = 0x0004_8004
+);
+
+size_tag!(
+    /// Sets the `x` and `y` position of the display within the framebuffer, in
+    /// pixels.
+    SetVirtualOffset = 0x0004_8009
+);
+
+word_tag!(
+    /// Requests the number of bytes between the starts of consecutive lines
+    /// of the framebuffer.
+    GetPitch = 0x0004_0008
+);
+
+/// Allocates the framebuffer, aligned to the given number of bytes, and
+/// returns its bus address and size in bytes. The address is 0 if the
+/// allocation failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocateBuffer(pub u32);
+
+impl Tag for AllocateBuffer {
+    const ID: u32 = 0x0004_0001;
+    const WORDS: usize = 2;
+    type Response = (u32, u32);
+
+    fn encode(&self, values: &mut [u32]) {
+        values[0] = self.0;
+    }
+
+    fn decode(values: &[u32]) -> (u32, u32) {
+        (values[0], values[1])
+    }
+}
+
+/// Sets the number of bits per pixel of the framebuffer and returns the depth
+/// actually set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SetDepth(pub u32);
+
+impl Tag for SetDepth {
+    const ID: u32 = 0x0004_8005;
+    const WORDS: usize = 1;
+    type Response = u32;
+
+    fn encode(&self, values: &mut [u32]) {
+        values[0] = self.0;
+    }
+
+    fn decode(values: &[u32]) -> u32 {
+        values[0]
+    }
+}
+
+/// Sets the pixel order of the framebuffer and returns the order actually
+/// set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SetPixelOrder(pub PixelOrder);
+
+impl Tag for SetPixelOrder {
+    const ID: u32 = 0x0004_8006;
+    const WORDS: usize = 1;
+    type Response = PixelOrder;
+
+    fn encode(&self, values: &mut [u32]) {
+        values[0] = self.0 as u32;
+    }
+
+    fn decode(values: &[u32]) -> PixelOrder {
+        match values[0] {
+            0 => PixelOrder::Bgr,
+            _ => PixelOrder::Rgb
+        }
+    }
+}
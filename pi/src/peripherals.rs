@@ -0,0 +1,175 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use gpio::{Gpio, Uninitialized, PinGroup, Pads};
+use interrupt::Controller;
+use timer::Timer;
+use mailbox::Mailbox;
+use framebuffer::{self, Framebuffer};
+use uart::{self, pl011, MiniUart, Pl011};
+
+/// Whether `Peripherals::take()` has already been called.
+static TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// The peripherals of the Raspberry Pi, handed out exactly once by
+/// `Peripherals::take()`.
+pub struct Peripherals {
+    /// The GPIO pins.
+    pub pins: Pins,
+    /// The GPIO pad control registers.
+    pub pads: Pads,
+    /// The mini UART.
+    pub uart: Uart,
+    /// The PL011 UART.
+    pub uart0: Uart0,
+    /// The ARM system timer.
+    pub timer: Timer,
+    /// The mailbox to the VideoCore firmware.
+    pub mailbox: Mailbox,
+    /// The display.
+    pub display: Display,
+    /// The interrupt controller.
+    pub interrupts: Controller,
+}
+
+impl Peripherals {
+    /// Returns the peripherals the first time it is called. Every subsequent
+    /// call returns `None`.
+    ///
+    /// Must only be called from a single core. With the MMU off, all memory is
+    /// Device memory, on which the exclusive accesses of an atomic swap may
+    /// never succeed, so `TAKEN` is checked and set with a plain load and
+    /// store, like the kernel's `Mutex` does.
+    pub fn take() -> Option<Peripherals> {
+        if TAKEN.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        TAKEN.store(true, Ordering::Relaxed);
+
+        Some(Peripherals {
+            pins: Pins { taken: [0; 2] },
+            pads: Pads::new(),
+            uart: Uart { _private: () },
+            uart0: Uart0 { _private: () },
+            timer: Timer::new(),
+            mailbox: Mailbox::new(),
+            display: Display { _private: () },
+            interrupts: Controller::new(),
+        })
+    }
+}
+
+/// The GPIO pins. Hands out each pin at most once until it is released.
+pub struct Pins {
+    taken: [u32; 2],
+}
+
+impl Pins {
+    /// Takes pin number `pin`. Returns `None` if `pin` > `53` or if the pin has
+    /// been taken and not released since.
+    pub fn take(&mut self, pin: u8) -> Option<Gpio<Uninitialized>> {
+        if pin > 53 {
+            return None;
+        }
+
+        let nbank = pin as usize / 32;
+        let n = pin as usize - nbank * 32;
+        if (self.taken[nbank] >> n) % 2 == 1 {
+            return None;
+        }
+
+        self.taken[nbank] |= 0b1 << n;
+        Some(unsafe { Gpio::new(pin) })
+    }
+
+    /// Returns a new, empty `PinGroup` for pins taken from `self`.
+    pub fn group<S>(&self) -> PinGroup<S> {
+        PinGroup::new()
+    }
+
+    /// Releases the pin of `gpio` so that it can be taken again. The pin keeps
+    /// its current configuration.
+    pub fn release<S>(&mut self, gpio: Gpio<S>) {
+        let nbank = gpio.pin() as usize / 32;
+        let n = gpio.pin() as usize - nbank * 32;
+        self.taken[nbank] &= !(0b1 << n);
+    }
+
+    /// Releases every pin in `group` so that each can be taken again. The pins
+    /// keep their current configuration.
+    pub fn release_group<S>(&mut self, group: PinGroup<S>) {
+        for gpio in group.into_pins() {
+            self.release(gpio);
+        }
+    }
+}
+
+/// The mini UART, before it is initialized.
+pub struct Uart {
+    _private: ()
+}
+
+impl Uart {
+    /// Returns a builder for the mini UART with `tx` and `rx` as its transmit
+    /// and receive pins, to configure the baud rate, frame size and flow
+    /// control before initializing it.
+    pub fn builder(self, tx: Gpio<Uninitialized>, rx: Gpio<Uninitialized>) -> uart::Builder {
+        uart::Builder::new(tx, rx)
+    }
+
+    /// Initializes the mini UART with `tx` and `rx` as its transmit and
+    /// receive pins and the default configuration. See `MiniUart::new()`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tx` cannot carry the mini UART's transmit line or `rx`
+    /// cannot carry its receive line. See `gpio::pinmux::pins_for`.
+    pub fn into_mini_uart(self, tx: Gpio<Uninitialized>, rx: Gpio<Uninitialized>) -> MiniUart {
+        self.builder(tx, rx).build().unwrap()
+    }
+}
+
+/// The PL011 UART, before it is initialized.
+pub struct Uart0 {
+    _private: ()
+}
+
+impl Uart0 {
+    /// Returns a builder for the PL011 with `tx` and `rx` as its transmit and
+    /// receive pins, to configure the baud rate and frame format before
+    /// initializing it.
+    pub fn builder(self, tx: Gpio<Uninitialized>, rx: Gpio<Uninitialized>) -> pl011::Builder {
+        pl011::Builder::new(tx, rx)
+    }
+
+    /// Initializes the PL011 with `tx` and `rx` as its transmit and receive
+    /// pins and the default configuration. See `Pl011::new()`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tx` cannot carry the PL011's transmit line or `rx` cannot
+    /// carry its receive line. See `gpio::pinmux::pins_for`.
+    pub fn into_pl011(self, tx: Gpio<Uninitialized>, rx: Gpio<Uninitialized>) -> Pl011 {
+        self.builder(tx, rx).build().unwrap()
+    }
+}
+
+/// The display, before a framebuffer is allocated for it.
+pub struct Display {
+    _private: ()
+}
+
+impl Display {
+    /// Asks the firmware for a `width` by `height` framebuffer with `depth`
+    /// bits per pixel through `mailbox`. See `Framebuffer::new()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the framebuffer could not be allocated. The display
+    /// cannot be used again afterwards.
+    pub fn into_framebuffer(self, mailbox: &mut Mailbox, width: u32, height: u32, depth: u32)
+        -> Result<Framebuffer, framebuffer::Error>
+    {
+        Framebuffer::new(mailbox, width, height, depth)
+    }
+}
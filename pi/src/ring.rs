@@ -0,0 +1,153 @@
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// The number of bytes a `Ring` holds. A power of two.
+pub const CAPACITY: usize = 256;
+
+/// A fixed-capacity, lock-free ring buffer of bytes with a single producer and
+/// a single consumer.
+///
+/// `push` may only be called by the producer and `pop` only by the consumer,
+/// for example an interrupt handler and the code it interrupts. Under that
+/// rule, both may run concurrently without further synchronization.
+pub struct Ring {
+    buffer: UnsafeCell<[u8; CAPACITY]>,
+    /// The number of bytes ever popped. Written only by the consumer.
+    head: AtomicUsize,
+    /// The number of bytes ever pushed. Written only by the producer.
+    tail: AtomicUsize,
+}
+
+unsafe impl Sync for Ring {  }
+
+impl Ring {
+    /// Returns a new, empty ring.
+    pub const fn new() -> Ring {
+        Ring {
+            buffer: UnsafeCell::new([0; CAPACITY]),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    /// Appends `byte` to the ring. Returns `false`, dropping `byte`, if the
+    /// ring is full. Must only be called by the producer.
+    pub fn push(&self, byte: u8) -> bool {
+        let tail = self.tail.load(Ordering::Relaxed);
+        let head = self.head.load(Ordering::Acquire);
+        if tail.wrapping_sub(head) == CAPACITY {
+            return false;
+        }
+
+        // The consumer does not read this slot until `tail` is published.
+        unsafe { (*self.buffer.get())[tail % CAPACITY] = byte; }
+        self.tail.store(tail.wrapping_add(1), Ordering::Release);
+        true
+    }
+
+    /// Removes and returns the oldest byte in the ring, or `None` if the ring
+    /// is empty. Must only be called by the consumer.
+    pub fn pop(&self) -> Option<u8> {
+        let head = self.head.load(Ordering::Relaxed);
+        let tail = self.tail.load(Ordering::Acquire);
+        if head == tail {
+            return None;
+        }
+
+        // The producer does not write this slot until `head` is published.
+        let byte = unsafe { (*self.buffer.get())[head % CAPACITY] };
+        self.head.store(head.wrapping_add(1), Ordering::Release);
+        Some(byte)
+    }
+
+    /// Returns the number of bytes in the ring.
+    ///
+    /// If the other side is running concurrently, the result may already be
+    /// out of date when it is returned.
+    pub fn len(&self) -> usize {
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Acquire);
+        ::core::cmp::min(tail.wrapping_sub(head), CAPACITY)
+    }
+
+    /// Returns `true` if the ring holds no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if the ring cannot accept another byte.
+    pub fn is_full(&self) -> bool {
+        self.len() == CAPACITY
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::Ordering;
+    use super::{Ring, CAPACITY};
+
+    #[test]
+    fn push_pop_in_order() {
+        let ring = Ring::new();
+        assert!(ring.is_empty());
+        assert_eq!(ring.pop(), None);
+
+        assert!(ring.push(1) && ring.push(2) && ring.push(3));
+        assert_eq!(ring.len(), 3);
+        assert_eq!((ring.pop(), ring.pop()), (Some(1), Some(2)));
+        assert!(ring.push(4));
+        assert_eq!((ring.pop(), ring.pop(), ring.pop()), (Some(3), Some(4), None));
+        assert!(ring.is_empty());
+    }
+
+    #[test]
+    fn full() {
+        let ring = Ring::new();
+        for i in 0..CAPACITY {
+            assert!(ring.push(i as u8));
+        }
+
+        assert!(ring.is_full());
+        assert!(!ring.push(0xFF));
+        assert_eq!(ring.len(), CAPACITY);
+
+        assert_eq!(ring.pop(), Some(0));
+        assert!(!ring.is_full());
+        assert!(ring.push(0xFF));
+        for i in 1..CAPACITY {
+            assert_eq!(ring.pop(), Some(i as u8));
+        }
+        assert_eq!((ring.pop(), ring.pop()), (Some(0xFF), None));
+    }
+
+    #[test]
+    fn wraps_around_the_buffer() {
+        let ring = Ring::new();
+        for round in 0..3 * CAPACITY / 2 {
+            assert!(ring.push(round as u8) && ring.push(!round as u8));
+            assert_eq!((ring.pop(), ring.pop()), (Some(round as u8), Some(!round as u8)));
+        }
+
+        assert!(ring.is_empty());
+    }
+
+    #[test]
+    fn wraps_around_the_counters() {
+        let ring = Ring::new();
+        let start = usize::max_value() - 2;
+        ring.head.store(start, Ordering::Relaxed);
+        ring.tail.store(start, Ordering::Relaxed);
+
+        for i in 0..CAPACITY {
+            assert!(ring.push(i as u8));
+        }
+
+        assert!(ring.is_full());
+        assert!(!ring.push(0xFF));
+        for i in 0..CAPACITY {
+            assert_eq!(ring.pop(), Some(i as u8));
+        }
+        assert!(ring.is_empty());
+        assert_eq!(ring.pop(), None);
+    }
+}
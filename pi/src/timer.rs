@@ -1,36 +1,159 @@
+use core::ops::{Add, Sub};
+use core::time::Duration;
+
+use arch::insn;
 use common::IO_BASE;
 use volatile::prelude::*;
-use volatile::{Volatile, ReadVolatile};
+use volatile::{Volatile, ReadVolatile, W1C, Mmio};
 
 /// The base address for the ARM system timer registers.
 const TIMER_REG_BASE: usize = IO_BASE + 0x3000;
 
-#[repr(C)]
-#[allow(non_snake_case)]
-struct Registers {
-    CS: Volatile<u32>,
-    CLO: ReadVolatile<u32>,
-    CHI: ReadVolatile<u32>,
-    COMPARE: [Volatile<u32>; 4]
+/// The system timer register block.
+const REGISTERS: Mmio<Registers> = unsafe { Mmio::new(TIMER_REG_BASE) };
+
+bitfields! {
+    /// The system timer control/status register (`CS`).
+    pub struct Cs(u32) {
+        /// Whether compare channel `n` has matched the counter.
+        rw matched[4], with_matched @ 0, 1: bool;
+    }
+}
+
+register_block! {
+    #[allow(non_snake_case)]
+    struct Registers {
+        0x00 => CS: W1C<Cs>,
+        0x04 => CLO: ReadVolatile<u32>,
+        0x08 => CHI: ReadVolatile<u32>,
+        0x0C => COMPARE: [Volatile<u32>; 4],
+    }
+}
+
+/// A compare channel of the system timer.
+///
+/// Each channel holds a 32-bit compare value. When the low 32 bits of the
+/// counter match it, the channel's match bit in `CS` is set until it is
+/// cleared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    C0 = 0,
+    C1 = 1,
+    C2 = 2,
+    C3 = 3,
+}
+
+impl Channel {
+    /// The channels that are free for use by the ARM core.
+    pub const AVAILABLE: [Channel; 2] = [Channel::C1, Channel::C3];
+
+    /// Returns `true` if the channel is used by the GPU firmware. Channels 0
+    /// and 2 are reserved this way and cannot be armed.
+    pub fn is_reserved(&self) -> bool {
+        match *self {
+            Channel::C0 | Channel::C2 => true,
+            Channel::C1 | Channel::C3 => false,
+        }
+    }
 }
 
 /// The Raspberry Pi ARM system timer.
 pub struct Timer {
-    registers: &'static mut Registers
+    registers: Mmio<Registers>,
+    /// Whether each channel was armed with a deadline that had already passed
+    /// when its compare value was written, so it will not match in time.
+    missed: [bool; 4],
 }
 
 impl Timer {
-    /// Returns a new instance of `Timer`.
-    pub fn new() -> Timer {
+    /// Returns a new instance of `Timer`. The public instance is handed out by
+    /// `Peripherals::take()`.
+    pub(crate) const fn new() -> Timer {
         Timer {
-            registers: unsafe { &mut *(TIMER_REG_BASE as *mut Registers) },
+            registers: REGISTERS,
+            missed: [false; 4],
         }
     }
 
     /// Reads the system timer's counter and returns the 64-bit counter value.
     /// The returned value is the number of elapsed microseconds.
+    ///
+    /// The two halves of the counter are read separately. `CHI` is re-read
+    /// until it is unchanged across the read of `CLO`, so a carry between the
+    /// two reads cannot produce a torn value.
     pub fn read(&self) -> u64 {
-        ((self.registers.CHI.read() as u64) << 32) | self.registers.CLO.read() as u64
+        loop {
+            let hi = self.registers.CHI.read();
+            let lo = self.registers.CLO.read();
+            if self.registers.CHI.read() == hi {
+                return ((hi as u64) << 32) | lo as u64;
+            }
+        }
+    }
+
+    /// Arms compare channel `channel` to match when the counter reaches
+    /// `deadline`, in microseconds, and clears any earlier match.
+    ///
+    /// Only the low 32 bits of `deadline` are compared, so the hardware will
+    /// not match a deadline that has already passed until the low 32 bits of
+    /// the counter wrap around, roughly 71 minutes later. Such a deadline is
+    /// instead reported as fired right away by `alarm_fired`.
+    ///
+    /// For the same reason, a deadline more than `u32::max_value()`
+    /// microseconds ahead would match early, once the low 32 bits come
+    /// around, and is rejected. `set_alarm_in` cannot exceed the limit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `channel` is reserved by the GPU or if `deadline` is more
+    /// than `u32::max_value()` microseconds, about 71.6 minutes, from now.
+    pub fn set_alarm_at(&mut self, channel: Channel, deadline: u64) {
+        if channel.is_reserved() {
+            panic!("Timer::set_alarm_at(): channel {:?} is reserved by the GPU", channel);
+        }
+
+        let now = self.read();
+        if deadline > now && deadline - now > u32::max_value() as u64 {
+            panic!("Timer::set_alarm_at(): deadline {}us is over 2^32us away", deadline);
+        }
+
+        // Clear first: a match between the two writes must not be lost.
+        self.clear_alarm(channel);
+        self.registers.COMPARE[channel as usize].write(deadline as u32);
+        self.missed[channel as usize] = self.read() >= deadline;
+    }
+
+    /// Arms compare channel `channel` to match `us` microseconds from now and
+    /// clears any earlier match. Returns the deadline, in microseconds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `channel` is reserved by the GPU.
+    pub fn set_alarm_in(&mut self, channel: Channel, us: u32) -> u64 {
+        let deadline = self.read() + us as u64;
+        self.set_alarm_at(channel, deadline);
+        deadline
+    }
+
+    /// Returns `true` if compare channel `channel` has matched the counter
+    /// since it was last cleared, or was armed with a deadline that had
+    /// already passed.
+    pub fn alarm_fired(&self, channel: Channel) -> bool {
+        self.missed[channel as usize] || self.registers.CS.read().matched(channel as usize)
+    }
+
+    /// Clears the match bit of compare channel `channel`. The compare value is
+    /// left as is.
+    pub fn clear_alarm(&mut self, channel: Channel) {
+        self.missed[channel as usize] = false;
+        self.registers.CS.clear(Cs::default().with_matched(channel as usize, true));
+    }
+
+    /// Spins until compare channel `channel` has matched the counter, then
+    /// clears its match bit.
+    pub fn wait_for_alarm(&mut self, channel: Channel) {
+        while !self.alarm_fired(channel) {  }
+        self.clear_alarm(channel);
     }
 }
 
@@ -39,15 +162,144 @@ pub fn current_time() -> u64 {
     Timer::new().read()
 }
 
+/// Registers the system timer as the clock behind `std::time::Instant`.
+#[cfg(all(feature = "std", not(feature = "mock")))]
+pub fn register_std_clock() {
+    ::std::time::set_clock(current_time);
+}
+
+/// Returns the number of whole microseconds in `duration`, or `None` if that
+/// overflows a `u64`.
+fn micros(duration: Duration) -> Option<u64> {
+    duration.as_secs()
+        .checked_mul(1_000_000)?
+        .checked_add(duration.subsec_micros() as u64)
+}
+
+/// A point in time, as read from the system timer.
+///
+/// An `Instant` has microsecond resolution and is monotonic: the system timer
+/// counter is 64 bits wide and does not wrap in practice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant(u64);
+
+impl Instant {
+    /// Returns the current instant.
+    pub fn now() -> Instant {
+        Instant(current_time())
+    }
+
+    /// Returns the instant `us` microseconds after the system timer started.
+    pub fn from_micros(us: u64) -> Instant {
+        Instant(us)
+    }
+
+    /// Returns the number of microseconds between the system timer starting
+    /// and `self`.
+    pub fn micros(&self) -> u64 {
+        self.0
+    }
+
+    /// Returns the amount of time elapsed from `earlier` to `self`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `earlier` is later than `self`.
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        match self.0.checked_sub(earlier.0) {
+            Some(us) => Duration::from_micros(us),
+            None => panic!("Instant::duration_since(): `earlier` is later than `self`")
+        }
+    }
+
+    /// Returns the amount of time elapsed since `self`.
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().duration_since(*self)
+    }
+
+    /// Returns `true` if `self` is not later than the current instant. Useful
+    /// for polling a deadline.
+    pub fn has_passed(&self) -> bool {
+        Instant::now() >= *self
+    }
+
+    /// Returns `self + duration`, or `None` if the result cannot be
+    /// represented. Durations are truncated to whole microseconds.
+    pub fn checked_add(&self, duration: Duration) -> Option<Instant> {
+        micros(duration).and_then(|us| self.0.checked_add(us)).map(Instant)
+    }
+
+    /// Returns `self - duration`, or `None` if the result would precede the
+    /// start of the system timer. Durations are truncated to whole
+    /// microseconds.
+    pub fn checked_sub(&self, duration: Duration) -> Option<Instant> {
+        micros(duration).and_then(|us| self.0.checked_sub(us)).map(Instant)
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    /// # Panics
+    ///
+    /// Panics on overflow. See `Instant::checked_add`.
+    fn add(self, duration: Duration) -> Instant {
+        self.checked_add(duration).expect("overflow when adding duration to instant")
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Instant;
+
+    /// # Panics
+    ///
+    /// Panics on underflow. See `Instant::checked_sub`.
+    fn sub(self, duration: Duration) -> Instant {
+        self.checked_sub(duration).expect("overflow when subtracting duration from instant")
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    /// # Panics
+    ///
+    /// Panics if `earlier` is later than `self`. See `Instant::duration_since`.
+    fn sub(self, earlier: Instant) -> Duration {
+        self.duration_since(earlier)
+    }
+}
+
+/// Returns the instant `duration` from now. Shorthand for
+/// `Instant::now() + duration`.
+pub fn deadline(duration: Duration) -> Instant {
+    Instant::now() + duration
+}
+
+/// Spins until `deadline` has passed.
+pub fn spin_sleep_until(deadline: Instant) {
+    while !deadline.has_passed() {}
+}
+
+/// Spins until at least `duration` has passed.
+pub fn spin_sleep(duration: Duration) {
+    spin_sleep_until(deadline(duration))
+}
+
 /// Spins until `us` microseconds have passed.
 pub fn spin_sleep_us(us: u64) {
-    let start_time = current_time();
-    while current_time() < start_time + us {}
+    spin_sleep(Duration::from_micros(us))
 }
 
 /// Spins until `ms` milliseconds have passed.
 pub fn spin_sleep_ms(ms: u64) {
-    for _ in 0..ms {
-        spin_sleep_us(1000);
+    spin_sleep(Duration::from_millis(ms))
+}
+
+/// Spins for at least `cycles` CPU cycles.
+#[inline(never)]
+pub fn spin_sleep_cycles(cycles: u32) {
+    for _ in 0..cycles {
+        unsafe { insn!("nop") }
     }
 }
@@ -0,0 +1,181 @@
+use core::time::Duration;
+
+use generic_timer;
+use super::ConfigError;
+
+/// The baud rates `measure` recognizes.
+pub const STANDARD_BAUDS: [u32; 11] = [
+    1200, 2400, 4800, 9600, 19200, 38400, 57600, 115200, 230400, 460800, 921600
+];
+
+/// The relative deviation, in percent, from a standard baud rate within which a
+/// measured rate is taken to be that rate.
+const TOLERANCE_PERCENT: u64 = 20;
+
+/// How long to wait, in microseconds, for the next edge within a frame before
+/// giving up on the frame. Enough for frames down to 1200 baud.
+const FRAME_TIMEOUT_US: u64 = 10_000;
+
+/// Error type for automatic baud rate detection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutobaudError {
+    /// No recognizable frame was received before the timeout expired.
+    TimedOut,
+    /// The detected baud rate, given here, cannot be configured.
+    InvalidBaud(u32),
+}
+
+impl From<ConfigError> for AutobaudError {
+    fn from(error: ConfigError) -> AutobaudError {
+        match error {
+            ConfigError::InvalidBaud(baud) => AutobaudError::InvalidBaud(baud),
+        }
+    }
+}
+
+/// Returns the positions, in bit times after the falling edge of the start
+/// bit, of the level changes on the line while a frame carrying `byte` is
+/// received, along with the line level after each change. Only the first
+/// `len` entries, the returned count, are valid.
+fn edges(byte: u8) -> ([(u64, bool); 10], usize) {
+    let mut edges = [(0, false); 10];
+    let mut len = 0;
+    let mut level = true;
+    for position in 0..10 {
+        // Start bit, eight data bits least significant bit first, stop bit.
+        let bit = match position {
+            0 => false,
+            9 => true,
+            n => (byte >> (n - 1)) & 1 == 1
+        };
+
+        if bit != level {
+            edges[len] = (position as u64, bit);
+            len += 1;
+            level = bit;
+        }
+    }
+
+    (edges, len)
+}
+
+/// Spins until `level()` returns `value`. Returns the generic timer tick at
+/// which it did, or `None` if the tick `deadline` passed first.
+fn wait_for_level<F>(level: &mut F, value: bool, deadline: Option<u64>) -> Option<u64>
+    where F: FnMut() -> bool
+{
+    loop {
+        if level() == value {
+            return Some(generic_timer::ticks());
+        }
+
+        if let Some(deadline) = deadline {
+            if generic_timer::ticks() >= deadline {
+                return None;
+            }
+        }
+    }
+}
+
+/// Returns the standard baud rate within `TOLERANCE_PERCENT` of `baud`, if
+/// any.
+fn snap(baud: u64) -> Option<u32> {
+    STANDARD_BAUDS.iter().cloned().find(|&standard| {
+        let standard = standard as u64;
+        let deviation = if baud > standard { baud - standard } else { standard - baud };
+        deviation * 100 <= standard * TOLERANCE_PERCENT
+    })
+}
+
+/// Detects the baud rate of a frame carrying `byte` by sampling the receive
+/// line with `level`, which returns `true` while the line is high.
+///
+/// The time between the start bit's falling edge and the last edge of the
+/// frame is measured with the generic timer, whose counter is fine enough for
+/// frames at 921600 baud, and matched against `STANDARD_BAUDS`. Frames that do
+/// not match, for example because a different character was sent, are
+/// ignored. If `timeout` is `Some`, gives up once it has passed; otherwise,
+/// waits indefinitely.
+///
+/// Returns the detected baud rate, or `Err(AutobaudError::TimedOut)`.
+pub fn measure<F>(mut level: F, byte: u8, timeout: Option<Duration>) -> Result<u32, AutobaudError>
+    where F: FnMut() -> bool
+{
+    let frequency = generic_timer::frequency();
+    let frame_timeout = generic_timer::duration_to_ticks(Duration::from_micros(FRAME_TIMEOUT_US));
+    let deadline = timeout.map(|timeout| {
+        generic_timer::ticks().saturating_add(generic_timer::duration_to_ticks(timeout))
+    });
+    let (edges, len) = edges(byte);
+    let bits = edges[len - 1].0;
+
+    loop {
+        // Wait for the line to be idle, then for the start bit.
+        wait_for_level(&mut level, true, deadline).ok_or(AutobaudError::TimedOut)?;
+        let start = wait_for_level(&mut level, false, deadline)
+            .ok_or(AutobaudError::TimedOut)?;
+
+        let mut end = Some(start);
+        for &(_, value) in edges[1..len].iter() {
+            let frame_deadline = end.map(|tick| tick + frame_timeout);
+            end = end.and_then(|_| wait_for_level(&mut level, value, frame_deadline));
+        }
+
+        if let Some(end) = end {
+            if end > start {
+                if let Some(baud) = snap(bits * frequency / (end - start)) {
+                    return Ok(baud);
+                }
+            }
+        }
+
+        if let Some(deadline) = deadline {
+            if generic_timer::ticks() >= deadline {
+                return Err(AutobaudError::TimedOut);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{edges, snap};
+
+    fn edge_list(byte: u8) -> Vec<(u64, bool)> {
+        let (edges, len) = edges(byte);
+        edges[..len].to_vec()
+    }
+
+    #[test]
+    fn edges_of_frames() {
+        // `0x55` alternates on every bit, from the start bit to the stop bit.
+        let alternating: Vec<_> = (0..10).map(|n| (n, n % 2 == 1)).collect();
+        assert_eq!(edge_list(0x55), alternating);
+
+        assert_eq!(edge_list(0xFF), vec![(0, false), (1, true)]);
+        assert_eq!(edge_list(0x00), vec![(0, false), (9, true)]);
+        assert_eq!(edge_list(b'\r'), vec![
+            (0, false), (1, true), (2, false), (3, true), (5, false), (9, true)
+        ]);
+    }
+
+    #[test]
+    fn snap_to_standard_bauds() {
+        assert_eq!(snap(1200), Some(1200));
+        assert_eq!(snap(115_200), Some(115_200));
+        assert_eq!(snap(921_600), Some(921_600));
+
+        // Within 20% either way.
+        assert_eq!(snap(92_160), Some(115_200));
+        assert_eq!(snap(138_240), Some(115_200));
+        assert_eq!(snap(8_000), Some(9600));
+    }
+
+    #[test]
+    fn snap_rejects_nonstandard_bauds() {
+        assert_eq!(snap(0), None);
+        assert_eq!(snap(92_159), None);
+        assert_eq!(snap(138_241), None);
+        assert_eq!(snap(1_200_000), None);
+    }
+}
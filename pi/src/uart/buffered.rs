@@ -0,0 +1,274 @@
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+
+use volatile::prelude::*;
+
+use arch;
+use interrupt::{Controller, Interrupt};
+use ring::Ring;
+use wait::{self, TimedOut};
+use super::{MiniUart, LineError, Lsr, Ier, Iir, REGISTERS};
+
+/// Bytes received by the interrupt handler, waiting to be read.
+static RX: Ring = Ring::new();
+
+/// Bytes written, waiting to be transmitted by the interrupt handler.
+static TX: Ring = Ring::new();
+
+/// Whether received bytes were dropped, by the mini UART or because `RX` was
+/// full, since the last error was reported.
+static RX_OVERRUN: AtomicBool = AtomicBool::new(false);
+
+/// Whether a `BufferedUart` exists and `handle_interrupt` should service the
+/// mini UART.
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Clears `RX_OVERRUN` and returns its previous value. An atomic swap may
+/// never succeed on Device memory, so this is a load and a store instead, with
+/// IRQs masked so that `handle_interrupt` cannot set the flag in between.
+fn take_overrun() -> bool {
+    arch::without_irqs(|| {
+        let overrun = RX_OVERRUN.load(Ordering::Acquire);
+        RX_OVERRUN.store(false, Ordering::Release);
+        overrun
+    })
+}
+
+/// Reads `LSR_REG`, recording a reported overrun in `RX_OVERRUN`.
+fn line_status() -> Lsr {
+    let lsr = REGISTERS.LSR_REG.take();
+    if lsr.rx_overrun() {
+        RX_OVERRUN.store(true, Ordering::Release);
+    }
+
+    lsr
+}
+
+/// Services the mini UART interrupt if it is pending: moves received bytes
+/// into the receive ring and refills the transmit FIFO from the transmit ring,
+/// disabling the transmit interrupt once the ring is empty.
+///
+/// This must be called from the kernel's IRQ handler and must not be called
+/// concurrently with itself. It does nothing while no `BufferedUart` exists.
+pub fn handle_interrupt() {
+    if !ACTIVE.load(Ordering::Acquire) || !Controller::new().is_pending(Interrupt::Aux) {
+        return;
+    }
+
+    let registers = REGISTERS;
+    while line_status().data_ready() {
+        if !RX.push(registers.IO_REG.read()) {
+            RX_OVERRUN.store(true, Ordering::Release);
+        }
+    }
+
+    while line_status().tx_available() {
+        match TX.pop() {
+            Some(byte) => registers.IO_REG.write(byte),
+            None => {
+                registers.IER_REG.modify(|r| r.with_tx_interrupt(false));
+                break;
+            }
+        }
+    }
+}
+
+/// The mini UART in interrupt-driven mode.
+///
+/// Received bytes are moved by `handle_interrupt` into a receive ring of
+/// `ring::CAPACITY` bytes, so no input is lost while the reader is busy for
+/// less time than it takes to fill the ring. Written bytes are queued in a
+/// transmit ring and sent by `handle_interrupt` as the transmit FIFO drains.
+///
+/// Every operation has a non-blocking `try_` variant. The blocking variants
+/// rely on `handle_interrupt` running, so they must not be called with IRQs
+/// masked.
+pub struct BufferedUart {
+    uart: MiniUart,
+}
+
+impl MiniUart {
+    /// Switches the mini UART to interrupt-driven mode: enables its receive
+    /// interrupt and the `Aux` interrupt in the interrupt controller. See
+    /// `BufferedUart`.
+    pub fn into_buffered(self) -> BufferedUart {
+        // Nothing services the rings while the mini UART's interrupts are
+        // disabled, so they can be emptied from here.
+        while RX.pop().is_some() {  }
+        while TX.pop().is_some() {  }
+        RX_OVERRUN.store(self.overrun.replace(false), Ordering::Release);
+
+        ACTIVE.store(true, Ordering::Release);
+        self.registers.IER_REG.write(Ier::default().with_rx_interrupt(true));
+        Controller::new().enable(Interrupt::Aux);
+
+        BufferedUart { uart: self }
+    }
+}
+
+impl BufferedUart {
+    /// Switches the mini UART back to polled mode. Bytes still queued for
+    /// transmission are sent first; received bytes not yet read are dropped.
+    pub fn into_polled(mut self) -> MiniUart {
+        self.flush();
+
+        Controller::new().disable(Interrupt::Aux);
+        self.uart.registers.IER_REG.write(Ier::default());
+        ACTIVE.store(false, Ordering::Release);
+        self.uart.overrun.set(take_overrun());
+        self.uart.registers.IIR_REG.write(Iir::default().with_clear_rx_fifo(true));
+
+        self.uart
+    }
+
+    /// Set the read timeout to `milliseconds` milliseconds.
+    pub fn set_read_timeout(&mut self, milliseconds: u32) {
+        self.uart.set_read_timeout(milliseconds);
+    }
+
+    /// Returns `true` if there is at least one byte ready to be read. This
+    /// method does not block.
+    pub fn has_byte(&self) -> bool {
+        !RX.is_empty()
+    }
+
+    /// Blocks until there is a byte ready to read, for at most the read
+    /// timeout if one is set.
+    ///
+    /// Returns `Ok(())` if a byte is ready to read. Returns `Err(TimedOut)` if
+    /// the timeout expired while waiting for a byte to be ready.
+    pub fn wait_for_byte(&self) -> Result<(), TimedOut> {
+        let timeout = self.uart.timeout.map(|ms| Duration::from_millis(ms as u64));
+        wait::until_timeout(timeout, || self.has_byte())
+    }
+
+    /// Reads a byte if one is ready. This method does not block.
+    pub fn try_read_byte(&mut self) -> Option<u8> {
+        RX.pop()
+    }
+
+    /// Reads a byte. Blocks indefinitely until a byte is ready to be read.
+    pub fn read_byte(&mut self) -> u8 {
+        loop {
+            if let Some(byte) = RX.pop() {
+                return byte;
+            }
+        }
+    }
+
+    /// Reads as many bytes as are ready into `buf`, without blocking. Returns
+    /// the number of bytes read.
+    pub fn try_read(&mut self, buf: &mut [u8]) -> usize {
+        let mut count = 0;
+        for slot in buf.iter_mut() {
+            match RX.pop() {
+                Some(byte) => *slot = byte,
+                None => break
+            }
+
+            count += 1;
+        }
+
+        count
+    }
+
+    /// Makes sure the interrupt handler picks up queued bytes.
+    fn start_tx(&mut self) {
+        self.uart.registers.IER_REG.modify(|r| r.with_tx_interrupt(true));
+    }
+
+    /// Queues `byte` for transmission if there is room in the transmit ring.
+    /// Returns `false` if there is not. This method does not block.
+    pub fn try_write_byte(&mut self, byte: u8) -> bool {
+        let queued = TX.push(byte);
+        if queued {
+            self.start_tx();
+        }
+        queued
+    }
+
+    /// Queues `byte` for transmission, blocking until there is room in the
+    /// transmit ring.
+    pub fn write_byte(&mut self, byte: u8) {
+        // The transmit interrupt stays enabled while the ring is not empty,
+        // so it only needs enabling once the byte is queued.
+        wait::until(|| TX.push(byte));
+        self.start_tx();
+    }
+
+    /// Queues as many bytes of `buf` as fit in the transmit ring, without
+    /// blocking. Returns the number of bytes queued.
+    pub fn try_write(&mut self, buf: &[u8]) -> usize {
+        let count = buf.iter().take_while(|&&byte| TX.push(byte)).count();
+        self.start_tx();
+        count
+    }
+
+    /// Blocks until every queued byte has been transmitted.
+    pub fn flush(&mut self) {
+        wait::until(|| TX.is_empty() && line_status().tx_idle());
+    }
+
+    /// Returns the receive line error seen since the last call, if any, and
+    /// clears it. Bytes dropped because the receive ring was full are
+    /// reported as `LineError::Overrun` as well.
+    pub fn take_error(&mut self) -> Option<LineError> {
+        if take_overrun() {
+            Some(LineError::Overrun)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Write for BufferedUart {
+    fn write_str(&mut self, s: &str) -> Result<(), fmt::Error> {
+        let mut prev = b'\x00';
+        for &b in s.as_bytes() {
+            if b == b'\n' && prev != b'\r' {
+                self.write_byte(b'\r');
+            }
+            self.write_byte(b);
+            prev = b;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+mod buffered_io {
+    use std::io;
+    use super::BufferedUart;
+
+    impl io::Read for BufferedUart {
+        /// Waits at most the read timeout for the first byte, then reads as
+        /// many bytes as are ready. Returns a receive error recorded since the
+        /// last read, if any, as an `InvalidData` error instead.
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if let Some(error) = self.take_error() {
+                return Err(error.into());
+            }
+
+            match self.wait_for_byte() {
+                Ok(()) => Ok(self.try_read(buf)),
+                Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut,
+                                             "Timed out when reading the first byte."))
+            }
+        }
+    }
+
+    impl io::Write for BufferedUart {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            for &byte in buf {
+                self.write_byte(byte);
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            BufferedUart::flush(self);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,583 @@
+use core::fmt;
+use core::cell::Cell;
+use core::time::Duration;
+
+use volatile::prelude::*;
+use volatile::{Volatile, ReadVolatile, ClearOnRead, Reserved, Mmio};
+
+use wait::{self, TimedOut};
+use common::IO_BASE;
+use gpio::{Gpio, AnyPin, Uninitialized, Signal};
+use gpio::any::Mode;
+
+pub mod buffered;
+pub mod pl011;
+pub mod autobaud;
+
+pub use self::buffered::BufferedUart;
+pub use self::pl011::Pl011;
+pub use self::autobaud::AutobaudError;
+
+/// The base address for the `MU` registers.
+const MU_REG_BASE: usize = IO_BASE + 0x215040;
+
+/// The `AUXENB` register from page 9 of the BCM2837 documentation.
+const AUX_ENABLES: Mmio<Volatile<u8>> = unsafe { Mmio::new(IO_BASE + 0x215004) };
+
+/// The `MU` register block.
+const REGISTERS: Mmio<Registers> = unsafe { Mmio::new(MU_REG_BASE) };
+
+field_enum! {
+    /// The data size of the mini UART.
+    pub enum DataSize: u8 {
+        Bits7 = 0b00,
+        Bits8 = 0b11,
+    }
+}
+
+field_enum! {
+    /// The cause of a pending mini UART interrupt.
+    pub enum InterruptId: u8 {
+        /// The transmit FIFO is empty.
+        TxEmpty = 0b01,
+        /// The receive FIFO holds at least one byte.
+        RxReady = 0b10,
+    }
+}
+
+bitfields! {
+    /// The `AUX_MU_IER_REG` interrupt enable register.
+    pub struct Ier(u8) {
+        /// Raises an interrupt while the receive FIFO holds at least one byte.
+        rw rx_interrupt, with_rx_interrupt @ 0, 1: bool;
+        /// Raises an interrupt while the transmit FIFO is empty.
+        rw tx_interrupt, with_tx_interrupt @ 1, 1: bool;
+    }
+
+    /// The `AUX_MU_IIR_REG` interrupt identify register.
+    pub struct Iir(u8) {
+        /// Clear while an interrupt is pending.
+        r no_interrupt @ 0, 1: bool;
+        /// The cause of the pending interrupt.
+        r interrupt_id @ 1, 2: InterruptId;
+        /// Clears the receive FIFO when written as set.
+        w with_clear_rx_fifo @ 1, 1: bool;
+        /// Clears the transmit FIFO when written as set.
+        w with_clear_tx_fifo @ 2, 1: bool;
+    }
+
+    /// The `AUX_MU_LCR_REG` line control register.
+    pub struct Lcr(u8) {
+        /// The data size of each frame.
+        rw data_size, with_data_size @ 0, 2: DataSize;
+        /// Pulls the TX line low while set.
+        rw break_, with_break @ 6, 1: bool;
+        /// Gives access to the baud rate register through `IO_REG` and
+        /// `IER_REG` while set.
+        rw dlab, with_dlab @ 7, 1: bool;
+    }
+
+    /// The `AUX_MU_LSR_REG` line status register.
+    pub struct Lsr(u8) {
+        /// At least one byte is waiting in the receive FIFO.
+        r data_ready @ 0, 1: bool;
+        /// A received byte was dropped because the receive FIFO was full.
+        r rx_overrun @ 1, 1: bool;
+        /// The transmit FIFO can accept at least one byte.
+        r tx_available @ 5, 1: bool;
+        /// The transmit FIFO is empty and the transmitter is idle.
+        r tx_idle @ 6, 1: bool;
+    }
+
+    /// The `AUX_MU_CNTL_REG` extra control register.
+    pub struct Cntl(u8) {
+        /// Enables the receiver.
+        rw rx_enable, with_rx_enable @ 0, 1: bool;
+        /// Enables the transmitter.
+        rw tx_enable, with_tx_enable @ 1, 1: bool;
+        /// Deasserts RTS automatically based on the receive FIFO level.
+        rw rts_flow, with_rts_flow @ 2, 1: bool;
+        /// Stops transmitting while CTS is deasserted.
+        rw cts_flow, with_cts_flow @ 3, 1: bool;
+        /// The receive FIFO level at which RTS is deasserted: 3, 2, 1 or 4
+        /// free entries for `0b00`, `0b01`, `0b10` and `0b11`.
+        rw rts_level, with_rts_level @ 4, 2: u8;
+        /// RTS is active low when clear, active high when set.
+        rw rts_assert_high, with_rts_assert_high @ 6, 1: bool;
+        /// CTS is active low when clear, active high when set.
+        rw cts_assert_high, with_cts_assert_high @ 7, 1: bool;
+    }
+}
+
+register_block! {
+    #[allow(non_snake_case)]
+    struct Registers {
+        0x00 => IO_REG: Volatile<u8>,
+        0x01 => __r0: [Reserved<u8>; 3],
+        0x04 => IER_REG: Volatile<Ier>,
+        0x05 => __r1: [Reserved<u8>; 3],
+        0x08 => IIR_REG: Volatile<Iir>,
+        0x09 => __r2: [Reserved<u8>; 3],
+        0x0C => LCR_REG: Volatile<Lcr>,
+        0x0D => __r3: [Reserved<u8>; 3],
+        0x10 => MCR_REG: Volatile<u8>,
+        0x11 => __r4: [Reserved<u8>; 3],
+        0x14 => LSR_REG: ClearOnRead<Lsr>,
+        0x15 => __r5: [Reserved<u8>; 3],
+        0x18 => MSR_REG: ReadVolatile<u8>,
+        0x19 => __r6: [Reserved<u8>; 3],
+        0x1C => SCRATCH: Volatile<u8>,
+        0x1D => __r7: [Reserved<u8>; 3],
+        0x20 => CNTL_REG: Volatile<Cntl>,
+        0x21 => __r8: [Reserved<u8>; 3],
+        0x24 => STAT_REG: ReadVolatile<u32>,
+        0x28 => BAUD_REG: Volatile<u16>,
+        0x2A => __r9: Reserved<u16>,
+    }
+}
+
+/// The core clock frequency, in Hz, that the firmware sets by default.
+pub const DEFAULT_CORE_CLOCK: u32 = 250_000_000;
+
+/// The baud rate the mini UART is configured for by default.
+pub const DEFAULT_BAUD: u32 = 115_200;
+
+/// An error on the receive line of a UART. The mini UART only reports
+/// `Overrun`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineError {
+    /// At least one received byte was dropped because the receive FIFO was
+    /// full.
+    Overrun,
+    /// A byte was received without a valid stop bit.
+    Framing,
+    /// A byte was received with the wrong parity.
+    Parity,
+    /// A break condition was received: the line was held low for longer than
+    /// a frame.
+    Break,
+}
+
+/// Error type for invalid UART configurations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The requested baud rate cannot be derived from the core clock.
+    InvalidBaud(u32),
+}
+
+/// The mini UART, its pins and its configuration, before it is initialized.
+///
+/// Obtained from `Peripherals`; see `peripherals::Uart::builder()`. Defaults
+/// to `DEFAULT_BAUD` at `DEFAULT_CORE_CLOCK`, 8-bit frames and no flow
+/// control.
+pub struct Builder {
+    tx: Gpio<Uninitialized>,
+    rx: AnyPin,
+    flow_control: Option<(Gpio<Uninitialized>, Gpio<Uninitialized>)>,
+    baud: u32,
+    core_clock: u32,
+    data_size: DataSize,
+    detected: Option<Result<u32, AutobaudError>>,
+}
+
+impl Builder {
+    /// Returns a builder for a mini UART transmitting on `tx` and receiving on
+    /// `rx`, with the default configuration.
+    pub(crate) fn new(tx: Gpio<Uninitialized>, rx: Gpio<Uninitialized>) -> Builder {
+        Builder {
+            tx: tx,
+            rx: AnyPin::from(rx),
+            flow_control: None,
+            baud: DEFAULT_BAUD,
+            core_clock: DEFAULT_CORE_CLOCK,
+            data_size: DataSize::Bits8,
+            detected: None,
+        }
+    }
+
+    /// Sets the requested baud rate.
+    pub fn baud(mut self, baud: u32) -> Builder {
+        self.baud = baud;
+        self
+    }
+
+    /// Sets the frequency of the core clock, in Hz, that the baud rate is
+    /// derived from. This must match the actual core clock.
+    pub fn core_clock(mut self, hz: u32) -> Builder {
+        self.core_clock = hz;
+        self
+    }
+
+    /// Sets the size of each frame.
+    pub fn data_size(mut self, data_size: DataSize) -> Builder {
+        self.data_size = data_size;
+        self
+    }
+
+    /// Enables RTS/CTS hardware flow control using `rts` and `cts`, usually
+    /// GPIO pins 17 and 16: in ALT5, GPIO17 is UART1_RTS and GPIO16 is
+    /// UART1_CTS.
+    pub fn flow_control(mut self, rts: Gpio<Uninitialized>, cts: Gpio<Uninitialized>) -> Builder {
+        self.flow_control = Some((rts, cts));
+        self
+    }
+
+    /// Detects the baud rate of the other end by sampling the receive pin as an
+    /// input while a frame carrying `byte`, typically `b'\r'`, arrives, and
+    /// sets the baud rate to it. See `autobaud::measure`. If `timeout` is
+    /// `Some`, gives up once it has passed.
+    ///
+    /// If no frame was recognized in time, or the detected baud rate cannot be
+    /// derived from the core clock, the baud rate is left unchanged. Either
+    /// way, the outcome is reported by `detected_baud()`.
+    pub fn autobaud(mut self, byte: u8, timeout: Option<Duration>) -> Builder {
+        // The pin is only handed to the UART by `build()`.
+        self.rx.set_mode(Mode::Input).unwrap();
+        let detected = {
+            let rx = &mut self.rx;
+            autobaud::measure(|| rx.level().unwrap(), byte, timeout)
+        };
+
+        let previous = self.baud;
+        self.detected = Some(match detected {
+            Ok(baud) => {
+                self.baud = baud;
+                match self.divider() {
+                    Ok(_) => Ok(baud),
+                    Err(error) => {
+                        self.baud = previous;
+                        Err(error.into())
+                    }
+                }
+            }
+            Err(error) => Err(error),
+        });
+
+        self
+    }
+
+    /// Returns the outcome of `autobaud()`: `None` if it was not called,
+    /// otherwise the detected baud rate or the reason none was detected.
+    pub fn detected_baud(&self) -> Option<Result<u32, AutobaudError>> {
+        self.detected
+    }
+
+    /// Returns the value of the baud rate register for the requested baud rate
+    /// and core clock, rounded to the nearest divider. The resulting baud rate
+    /// is `core_clock / (8 * (divider + 1))`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ConfigError::InvalidBaud` if the divider is out of range.
+    pub fn divider(&self) -> Result<u16, ConfigError> {
+        let (baud, clock) = (self.baud as u64, self.core_clock as u64);
+        if baud == 0 {
+            return Err(ConfigError::InvalidBaud(self.baud));
+        }
+
+        let n = (clock + 4 * baud) / (8 * baud);
+        if n < 1 || n > 0x10000 {
+            return Err(ConfigError::InvalidBaud(self.baud));
+        }
+
+        Ok((n - 1) as u16)
+    }
+
+    /// Returns the baud rate the mini UART will actually run at, which differs
+    /// slightly from the requested one unless it divides the core clock.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ConfigError::InvalidBaud` if the requested baud rate is out of
+    /// range.
+    pub fn actual_baud(&self) -> Result<u32, ConfigError> {
+        let divider = self.divider()? as u32;
+        Ok(self.core_clock / (8 * (divider + 1)))
+    }
+
+    /// Initializes the mini UART by enabling it as an auxiliary peripheral,
+    /// setting the data size and baud rate, routing the transmit, receive
+    /// and, if enabled, flow control lines to their pins, and finally enabling
+    /// the UART transmitter and receiver.
+    ///
+    /// By default, reads will never time out. To set a read timeout, use
+    /// `set_read_timeout()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ConfigError::InvalidBaud` if the requested baud rate is out of
+    /// range. Nothing is initialized in that case.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a pin cannot carry the line it is given for. See
+    /// `gpio::pinmux::pins_for`.
+    pub fn build(self) -> Result<MiniUart, ConfigError> {
+        let divider = self.divider()?;
+
+        // Enable the mini UART as an auxiliary device.
+        AUX_ENABLES.or_mask(1);
+        let registers = REGISTERS;
+
+        // Keep the UART quiet while it is being configured.
+        registers.CNTL_REG.write(Cntl::default());
+        registers.LCR_REG.write(Lcr::default().with_data_size(self.data_size));
+        registers.BAUD_REG.write(divider);
+
+        self.tx.into_signal(Signal::Uart1Tx);
+        self.rx.into_uninitialized().into_signal(Signal::Uart1Rx);
+
+        let flow = self.flow_control.is_some();
+        if let Some((rts, cts)) = self.flow_control {
+            rts.into_signal(Signal::Uart1Rts);
+            cts.into_signal(Signal::Uart1Cts);
+        }
+
+        registers.CNTL_REG.write(Cntl::default()
+            .with_rx_enable(true)
+            .with_tx_enable(true)
+            .with_rts_flow(flow)
+            .with_cts_flow(flow));
+
+        Ok(MiniUart {
+            registers: registers,
+            timeout: None,
+            overrun: Cell::new(false),
+        })
+    }
+}
+
+/// The Raspberry Pi's "mini UART".
+pub struct MiniUart {
+    registers: Mmio<Registers>,
+    timeout: Option<u32>,
+    /// Whether an overrun was seen in `LSR_REG` and not yet reported. Reading
+    /// `LSR_REG` clears its overrun bit, so it is recorded on every read.
+    overrun: Cell<bool>,
+}
+
+impl MiniUart {
+    /// Initializes the mini UART with the default configuration: 8-bit frames
+    /// at ~115200 baud, transmitting on GPIO pin 14 and receiving on GPIO pin
+    /// 15. See `Builder::build()`.
+    ///
+    /// The mini UART should be obtained through `Peripherals::take()` instead,
+    /// which hands it out only once.
+    ///
+    /// # Safety
+    ///
+    /// No other `MiniUart` may be in use, and GPIO pins 14 and 15 must not be
+    /// in use elsewhere.
+    pub unsafe fn new() -> MiniUart {
+        Builder::new(Gpio::new(14), Gpio::new(15)).build().unwrap()
+    }
+
+    /// Set the read timeout to `milliseconds` milliseconds.
+    pub fn set_read_timeout(&mut self, milliseconds: u32) {
+        self.timeout = Some(milliseconds);
+    }
+
+    /// Write the byte `byte`. This method blocks until there is space available
+    /// in the output FIFO.
+    pub fn write_byte(&mut self, byte: u8) {
+        // while !self.registers.STAT_REG.has_mask(0b1 << 8) {} // keeps spining when FIFO is full
+
+        wait::until(|| self.line_status().tx_available());
+        self.registers.IO_REG.write(byte);
+    }
+
+    /// Returns `true` if there is at least one byte ready to be read. If this
+    /// method returns `true`, a subsequent call to `read_byte` is guaranteed to
+    /// return immediately. This method does not block.
+    pub fn has_byte(&self) -> bool {
+        // I cannot tell the difference between the two implementations from the datasheet.
+
+        // self.registers.STAT_REG.has_mask(0b1)
+
+        self.line_status().data_ready()
+    }
+
+    /// Reads `LSR_REG`, recording any error it reports.
+    fn line_status(&self) -> Lsr {
+        let lsr = self.registers.LSR_REG.take();
+        if lsr.rx_overrun() {
+            self.overrun.set(true);
+        }
+
+        lsr
+    }
+
+    /// Returns the receive line error seen since the last call, if any, and
+    /// clears it.
+    pub fn take_error(&mut self) -> Option<LineError> {
+        self.line_status();
+        if self.overrun.replace(false) {
+            Some(LineError::Overrun)
+        } else {
+            None
+        }
+    }
+
+    /// Blocks until there is a byte ready to read. If a read timeout is set,
+    /// this method blocks for at most that amount of time. Otherwise, this
+    /// method blocks indefinitely until there is a byte to read.
+    ///
+    /// Returns `Ok(())` if a byte is ready to read. Returns `Err(TimedOut)` if
+    /// the timeout expired while waiting for a byte to be ready. If this method
+    /// returns `Ok(())`, a subsequent call to `read_byte` is guaranteed to
+    /// return immediately.
+    pub fn wait_for_byte(&self) -> Result<(), TimedOut> {
+        let timeout = self.timeout.map(|ms| Duration::from_millis(ms as u64));
+        wait::until_timeout(timeout, || self.has_byte())
+    }
+
+    /// Reads a byte. Blocks indefinitely until a byte is ready to be read.
+    pub fn read_byte(&mut self) -> u8 {
+        wait::until(|| self.has_byte());
+        self.registers.IO_REG.read()
+    }
+
+    /// Reads a byte like `read_byte`, first reporting any receive line error.
+    ///
+    /// Returns `Err(LineError::Overrun)` if bytes were dropped since the last
+    /// error was reported; the bytes still in the receive FIFO are then read by
+    /// subsequent calls.
+    pub fn read_byte_checked(&mut self) -> Result<u8, LineError> {
+        wait::until(|| self.has_byte());
+        match self.take_error() {
+            Some(error) => Err(error),
+            None => Ok(self.registers.IO_REG.read())
+        }
+    }
+}
+
+// FIXME: Implement `fmt::Write` for `MiniUart`. A b'\r' byte should be written
+// before writing any b'\n' byte.
+impl fmt::Write for MiniUart {
+    fn write_str(&mut self, s: &str) -> Result<(), fmt::Error> {
+        let mut prev = b'\x00';
+        for b in s.as_bytes().iter() {
+            if *b == b'\n' && prev != b'\r' {
+                self.write_byte(b'\r');
+            }
+            self.write_byte(*b);
+            prev = *b;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+mod uart_io {
+    use std::io;
+    use super::{MiniUart, LineError};
+
+    impl From<LineError> for io::Error {
+        fn from(error: LineError) -> io::Error {
+            let message = match error {
+                LineError::Overrun => "Received bytes were dropped: receive FIFO overrun.",
+                LineError::Framing => "A byte was received without a valid stop bit.",
+                LineError::Parity => "A byte was received with the wrong parity.",
+                LineError::Break => "A break condition was received.",
+            };
+
+            io::Error::new(io::ErrorKind::InvalidData, message)
+        }
+    }
+
+    // FIXME: Implement `io::Read` and `io::Write` for `MiniUart`.
+    //
+    // The `io::Read::read()` implementation must respect the read timeout by
+    // waiting at most that time for the _first byte_. It should not wait for
+    // any additional bytes but _should_ read as many bytes as possible. If the
+    // read times out, an error of kind `TimedOut` should be returned.
+    //
+    // The `io::Write::write()` method must write all of the requested bytes
+    // before returning.
+
+    impl io::Read for MiniUart {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            // Report bytes lost before this read; those still in the FIFO are
+            // returned by the next one.
+            if let Some(error) = self.take_error() {
+                return Err(error.into());
+            }
+
+            let mut count = 0;
+            match self.wait_for_byte() {
+                Ok(()) => {
+                    for byte in buf.iter_mut() {
+                        if self.has_byte() {
+                            *byte = self.read_byte();
+                            count += 1;
+                        }
+                        else {
+                            break;
+                        }
+                    }
+                    Ok(count)
+                }
+                Err(_) => {
+                    Err(io::Error::new(
+                            io::ErrorKind::TimedOut,
+                            "Timed out when reading the first byte."))
+                }
+            }
+        }
+    }
+
+    impl io::Write for MiniUart {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            for byte in buf.iter() {
+                self.write_byte(*byte);
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use volatile::mock::{self, RegisterFile};
+    use gpio::Gpio;
+    use super::{Builder, ConfigError, MiniUart, MU_REG_BASE};
+
+    fn builder() -> Builder {
+        mock::install(RegisterFile::new());
+        unsafe { Builder::new(Gpio::new(14), Gpio::new(15)) }
+    }
+
+    #[test]
+    fn new_programs_baud_reg() {
+        let regs = mock::install(RegisterFile::new());
+
+        unsafe { MiniUart::new() };
+
+        // 250 MHz / (8 * 115200) - 1, rounded to the nearest divider.
+        assert_eq!(regs.borrow().writes_to(MU_REG_BASE + 0x28), vec![270]);
+    }
+
+    #[test]
+    fn divider() {
+        assert_eq!(builder().divider(), Ok(270));
+        assert_eq!(builder().actual_baud(), Ok(115_313));
+        assert_eq!(builder().baud(9600).divider(), Ok(3254));
+        assert_eq!(builder().baud(9600).core_clock(400_000_000).divider(), Ok(5207));
+
+        // The extremes of the 16-bit baud rate register.
+        assert_eq!(builder().baud(31_250_000).divider(), Ok(0));
+        assert_eq!(builder().baud(477).divider(), Ok(65513));
+    }
+
+    #[test]
+    fn divider_out_of_range() {
+        assert_eq!(builder().baud(0).divider(), Err(ConfigError::InvalidBaud(0)));
+        assert_eq!(builder().baud(100_000_000).divider(), Err(ConfigError::InvalidBaud(100_000_000)));
+        assert_eq!(builder().baud(400).divider(), Err(ConfigError::InvalidBaud(400)));
+        assert_eq!(builder().baud(400).actual_baud(), Err(ConfigError::InvalidBaud(400)));
+    }
+}
@@ -0,0 +1,531 @@
+use core::fmt;
+use core::cell::Cell;
+use core::time::Duration;
+
+use volatile::prelude::*;
+use volatile::{Volatile, ReadVolatile, WriteVolatile, Reserved, Mmio};
+
+use wait::{self, TimedOut};
+use common::IO_BASE;
+use gpio::{Gpio, Uninitialized, Signal};
+use super::{LineError, ConfigError};
+
+/// The base address of the PL011 registers.
+const PL011_REG_BASE: usize = IO_BASE + 0x201000;
+
+/// The PL011 register block.
+const REGISTERS: Mmio<Registers> = unsafe { Mmio::new(PL011_REG_BASE) };
+
+/// The frequency of the PL011's reference clock, in Hz, that the firmware sets
+/// by default (`init_uart_clock`).
+pub const DEFAULT_UART_CLOCK: u32 = 48_000_000;
+
+field_enum! {
+    /// The number of data bits in each frame.
+    pub enum WordLength: u32 {
+        Bits5 = 0b00,
+        Bits6 = 0b01,
+        Bits7 = 0b10,
+        Bits8 = 0b11,
+    }
+}
+
+field_enum! {
+    /// A FIFO fill level at which an interrupt is raised.
+    pub enum FifoLevel: u32 {
+        /// 1/8 full: 2 bytes.
+        Eighth = 0b000,
+        /// 1/4 full: 4 bytes.
+        Quarter = 0b001,
+        /// 1/2 full: 8 bytes.
+        Half = 0b010,
+        /// 3/4 full: 12 bytes.
+        ThreeQuarters = 0b011,
+        /// 7/8 full: 14 bytes.
+        SevenEighths = 0b100,
+    }
+}
+
+/// The parity bit of each frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parity {
+    None,
+    Odd,
+    Even,
+}
+
+/// The number of stop bits of each frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopBits {
+    One,
+    Two,
+}
+
+bitfields! {
+    /// The data register (`DR`), as read.
+    pub struct Dr(u32) {
+        /// The received byte.
+        r data @ 0, 8: u8;
+        /// The byte was received without a valid stop bit.
+        r framing_error @ 8, 1: bool;
+        /// The byte was received with the wrong parity.
+        r parity_error @ 9, 1: bool;
+        /// A break condition was received.
+        r break_error @ 10, 1: bool;
+        /// A byte was dropped because the receive FIFO was full.
+        r overrun_error @ 11, 1: bool;
+    }
+
+    /// The flag register (`FR`).
+    pub struct Fr(u32) {
+        /// The UART is transmitting.
+        r busy @ 3, 1: bool;
+        /// The receive FIFO is empty.
+        r rx_empty @ 4, 1: bool;
+        /// The transmit FIFO is full.
+        r tx_full @ 5, 1: bool;
+        /// The receive FIFO is full.
+        r rx_full @ 6, 1: bool;
+        /// The transmit FIFO is empty.
+        r tx_empty @ 7, 1: bool;
+    }
+
+    /// The line control register (`LCRH`).
+    pub struct Lcrh(u32) {
+        /// Sends a break while set.
+        rw break_, with_break @ 0, 1: bool;
+        /// Enables parity.
+        rw parity_enable, with_parity_enable @ 1, 1: bool;
+        /// Selects even parity when set, odd parity when clear.
+        rw even_parity, with_even_parity @ 2, 1: bool;
+        /// Sends two stop bits when set.
+        rw two_stop_bits, with_two_stop_bits @ 3, 1: bool;
+        /// Enables the transmit and receive FIFOs.
+        rw fifo_enable, with_fifo_enable @ 4, 1: bool;
+        /// The number of data bits in each frame.
+        rw word_length, with_word_length @ 5, 2: WordLength;
+    }
+
+    /// The control register (`CR`).
+    pub struct Cr(u32) {
+        /// Enables the UART.
+        rw enable, with_enable @ 0, 1: bool;
+        /// Enables the transmitter.
+        rw tx_enable, with_tx_enable @ 8, 1: bool;
+        /// Enables the receiver.
+        rw rx_enable, with_rx_enable @ 9, 1: bool;
+    }
+
+    /// The interrupt FIFO level select register (`IFLS`).
+    pub struct Ifls(u32) {
+        /// The transmit FIFO level of the transmit interrupt.
+        rw tx_level, with_tx_level @ 0, 3: FifoLevel;
+        /// The receive FIFO level of the receive interrupt.
+        rw rx_level, with_rx_level @ 3, 3: FifoLevel;
+    }
+}
+
+register_block! {
+    #[allow(non_snake_case)]
+    struct Registers {
+        0x00 => DR: Volatile<u32>,
+        0x04 => RSRECR: Volatile<u32>,
+        0x08 => __r0: [Reserved<u32>; 4],
+        0x18 => FR: ReadVolatile<Fr>,
+        0x1C => __r1: Reserved<u32>,
+        0x20 => ILPR: Volatile<u32>,
+        0x24 => IBRD: Volatile<u32>,
+        0x28 => FBRD: Volatile<u32>,
+        0x2C => LCRH: Volatile<Lcrh>,
+        0x30 => CR: Volatile<Cr>,
+        0x34 => IFLS: Volatile<Ifls>,
+        0x38 => IMSC: Volatile<u32>,
+        0x3C => RIS: ReadVolatile<u32>,
+        0x40 => MIS: ReadVolatile<u32>,
+        0x44 => ICR: WriteVolatile<u32>,
+        0x48 => DMACR: Volatile<u32>,
+    }
+}
+
+/// The PL011, its pins and its configuration, before it is initialized.
+///
+/// Obtained from `Peripherals`; see `peripherals::Uart0::builder()`. Defaults
+/// to `uart::DEFAULT_BAUD` at `DEFAULT_UART_CLOCK`, 8 data bits, no parity,
+/// one stop bit, and FIFO interrupt levels of one half.
+pub struct Builder {
+    tx: Gpio<Uninitialized>,
+    rx: Gpio<Uninitialized>,
+    baud: u32,
+    uart_clock: u32,
+    word_length: WordLength,
+    parity: Parity,
+    stop_bits: StopBits,
+    tx_level: FifoLevel,
+    rx_level: FifoLevel,
+}
+
+impl Builder {
+    /// Returns a builder for a PL011 transmitting on `tx` and receiving on
+    /// `rx`, with the default configuration.
+    pub(crate) fn new(tx: Gpio<Uninitialized>, rx: Gpio<Uninitialized>) -> Builder {
+        Builder {
+            tx: tx,
+            rx: rx,
+            baud: super::DEFAULT_BAUD,
+            uart_clock: DEFAULT_UART_CLOCK,
+            word_length: WordLength::Bits8,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            tx_level: FifoLevel::Half,
+            rx_level: FifoLevel::Half,
+        }
+    }
+
+    /// Sets the requested baud rate.
+    pub fn baud(mut self, baud: u32) -> Builder {
+        self.baud = baud;
+        self
+    }
+
+    /// Sets the frequency of the UART reference clock, in Hz, that the baud
+    /// rate is derived from. This must match the actual clock.
+    pub fn uart_clock(mut self, hz: u32) -> Builder {
+        self.uart_clock = hz;
+        self
+    }
+
+    /// Sets the number of data bits in each frame.
+    pub fn word_length(mut self, word_length: WordLength) -> Builder {
+        self.word_length = word_length;
+        self
+    }
+
+    /// Sets the parity of each frame.
+    pub fn parity(mut self, parity: Parity) -> Builder {
+        self.parity = parity;
+        self
+    }
+
+    /// Sets the number of stop bits of each frame.
+    pub fn stop_bits(mut self, stop_bits: StopBits) -> Builder {
+        self.stop_bits = stop_bits;
+        self
+    }
+
+    /// Sets the FIFO levels at which the transmit and receive interrupts are
+    /// raised.
+    pub fn fifo_levels(mut self, tx: FifoLevel, rx: FifoLevel) -> Builder {
+        self.tx_level = tx;
+        self.rx_level = rx;
+        self
+    }
+
+    /// Returns the integer and fractional baud rate divisors (`IBRD`, `FBRD`)
+    /// for the requested baud rate and reference clock. The divisor is
+    /// `uart_clock / (16 * baud)`, with the fraction rounded to 1/64.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ConfigError::InvalidBaud` if the divisor is out of range.
+    pub fn divisors(&self) -> Result<(u16, u8), ConfigError> {
+        let (baud, clock) = (self.baud as u64, self.uart_clock as u64);
+        if baud == 0 {
+            return Err(ConfigError::InvalidBaud(self.baud));
+        }
+
+        // The divisor in units of 1/64, rounded to the nearest unit.
+        let divisor = (clock * 8 / baud + 1) / 2;
+        let (ibrd, fbrd) = (divisor >> 6, divisor & 0b11_1111);
+        if ibrd < 1 || ibrd > 0xFFFF {
+            return Err(ConfigError::InvalidBaud(self.baud));
+        }
+
+        Ok((ibrd as u16, fbrd as u8))
+    }
+
+    /// Returns the baud rate the PL011 will actually run at.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ConfigError::InvalidBaud` if the requested baud rate is out of
+    /// range.
+    pub fn actual_baud(&self) -> Result<u32, ConfigError> {
+        let (ibrd, fbrd) = self.divisors()?;
+        let divisor = ((ibrd as u64) << 6) | fbrd as u64;
+        Ok((self.uart_clock as u64 * 4 / divisor) as u32)
+    }
+
+    /// Initializes the PL011: disables it, programs the baud rate divisors,
+    /// frame format and FIFO levels, routes the transmit and receive lines to
+    /// their pins, and finally enables the UART, its transmitter and its
+    /// receiver.
+    ///
+    /// By default, reads will never time out. To set a read timeout, use
+    /// `set_read_timeout()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ConfigError::InvalidBaud` if the requested baud rate is out of
+    /// range. Nothing is initialized in that case.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a pin cannot carry the line it is given for. See
+    /// `gpio::pinmux::pins_for`.
+    pub fn build(self) -> Result<Pl011, ConfigError> {
+        let (ibrd, fbrd) = self.divisors()?;
+        let registers = REGISTERS;
+
+        // Disable the UART, let it finish the current frame, then flush the
+        // transmit FIFO by disabling the FIFOs.
+        registers.CR.write(Cr::default());
+        wait::until(|| !registers.FR.read().busy());
+        registers.LCRH.modify(|r| r.with_fifo_enable(false));
+        registers.ICR.write(0x7FF);
+
+        // `IBRD` and `FBRD` are latched by the write to `LCRH`.
+        registers.IBRD.write(ibrd as u32);
+        registers.FBRD.write(fbrd as u32);
+        registers.LCRH.write(Lcrh::default()
+            .with_word_length(self.word_length)
+            .with_parity_enable(self.parity != Parity::None)
+            .with_even_parity(self.parity == Parity::Even)
+            .with_two_stop_bits(self.stop_bits == StopBits::Two)
+            .with_fifo_enable(true));
+        registers.IFLS.write(Ifls::default()
+            .with_tx_level(self.tx_level)
+            .with_rx_level(self.rx_level));
+
+        self.tx.into_signal(Signal::Uart0Tx);
+        self.rx.into_signal(Signal::Uart0Rx);
+
+        registers.CR.write(Cr::default()
+            .with_enable(true)
+            .with_tx_enable(true)
+            .with_rx_enable(true));
+
+        Ok(Pl011 {
+            registers: registers,
+            timeout: None,
+            error: Cell::new(None),
+        })
+    }
+}
+
+/// The Raspberry Pi's PL011 UART (`UART0`).
+///
+/// Unlike the mini UART, the PL011 runs off its own reference clock, so its
+/// baud rate does not change with the core clock. On the Raspberry Pi 3 the
+/// firmware connects it to the Bluetooth module by default; it must be freed
+/// with the `disable-bt` or `miniuart-bt` overlay before it can be used on
+/// GPIO pins 14 and 15.
+pub struct Pl011 {
+    registers: Mmio<Registers>,
+    timeout: Option<u32>,
+    /// The first receive error seen and not yet reported.
+    error: Cell<Option<LineError>>,
+}
+
+impl Pl011 {
+    /// Initializes the PL011 with the default configuration: 8N1 frames at
+    /// ~115200 baud, transmitting on GPIO pin 14 and receiving on GPIO pin 15.
+    /// See `Builder::build()`.
+    ///
+    /// The PL011 should be obtained through `Peripherals::take()` instead,
+    /// which hands it out only once.
+    ///
+    /// # Safety
+    ///
+    /// No other `Pl011` may be in use, and GPIO pins 14 and 15 must not be in
+    /// use elsewhere.
+    pub unsafe fn new() -> Pl011 {
+        Builder::new(Gpio::new(14), Gpio::new(15)).build().unwrap()
+    }
+
+    /// Set the read timeout to `milliseconds` milliseconds.
+    pub fn set_read_timeout(&mut self, milliseconds: u32) {
+        self.timeout = Some(milliseconds);
+    }
+
+    /// Write the byte `byte`. This method blocks until there is space available
+    /// in the output FIFO.
+    pub fn write_byte(&mut self, byte: u8) {
+        wait::until(|| !self.registers.FR.read().tx_full());
+        self.registers.DR.write(byte as u32);
+    }
+
+    /// Returns `true` if there is at least one byte ready to be read. If this
+    /// method returns `true`, a subsequent call to `read_byte` is guaranteed to
+    /// return immediately. This method does not block.
+    pub fn has_byte(&self) -> bool {
+        !self.registers.FR.read().rx_empty()
+    }
+
+    /// Blocks until there is a byte ready to read. If a read timeout is set,
+    /// this method blocks for at most that amount of time. Otherwise, this
+    /// method blocks indefinitely until there is a byte to read.
+    ///
+    /// Returns `Ok(())` if a byte is ready to read. Returns `Err(TimedOut)` if
+    /// the timeout expired while waiting for a byte to be ready.
+    pub fn wait_for_byte(&self) -> Result<(), TimedOut> {
+        let timeout = self.timeout.map(|ms| Duration::from_millis(ms as u64));
+        wait::until_timeout(timeout, || self.has_byte())
+    }
+
+    /// Reads the next entry of the receive FIFO, blocking until there is one.
+    /// Returns the received byte and the error it was received with, if any.
+    fn read_entry(&mut self) -> (u8, Option<LineError>) {
+        wait::until(|| self.has_byte());
+        let dr = Dr::from_bits(self.registers.DR.read());
+        let error = if dr.overrun_error() {
+            Some(LineError::Overrun)
+        } else if dr.break_error() {
+            Some(LineError::Break)
+        } else if dr.parity_error() {
+            Some(LineError::Parity)
+        } else if dr.framing_error() {
+            Some(LineError::Framing)
+        } else {
+            None
+        };
+
+        (dr.data(), error)
+    }
+
+    /// Reads a byte. Blocks indefinitely until a byte is ready to be read. If
+    /// the byte was received with an error, the error is recorded, to be
+    /// reported by `take_error`.
+    pub fn read_byte(&mut self) -> u8 {
+        let (byte, error) = self.read_entry();
+        if self.error.get().is_none() {
+            self.error.set(error);
+        }
+
+        byte
+    }
+
+    /// Reads a byte like `read_byte`, first reporting any recorded receive
+    /// error.
+    ///
+    /// Returns `Err` if an error was recorded or the byte was received with an
+    /// error; in the latter case, the byte is discarded.
+    pub fn read_byte_checked(&mut self) -> Result<u8, LineError> {
+        if let Some(error) = self.error.replace(None) {
+            return Err(error);
+        }
+
+        match self.read_entry() {
+            (_, Some(error)) => Err(error),
+            (byte, None) => Ok(byte)
+        }
+    }
+
+    /// Returns the first receive error recorded since the last call, if any,
+    /// and clears it.
+    pub fn take_error(&mut self) -> Option<LineError> {
+        self.error.replace(None)
+    }
+
+    /// Blocks until every byte in the transmit FIFO has been transmitted.
+    pub fn flush(&mut self) {
+        wait::until(|| !self.registers.FR.read().busy());
+    }
+}
+
+impl fmt::Write for Pl011 {
+    fn write_str(&mut self, s: &str) -> Result<(), fmt::Error> {
+        let mut prev = b'\x00';
+        for &b in s.as_bytes() {
+            if b == b'\n' && prev != b'\r' {
+                self.write_byte(b'\r');
+            }
+            self.write_byte(b);
+            prev = b;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+mod pl011_io {
+    use std::io;
+    use super::Pl011;
+
+    impl io::Read for Pl011 {
+        /// Waits at most the read timeout for the first byte, then reads as
+        /// many bytes as are ready. Returns a receive error recorded since the
+        /// last read, if any, as an `InvalidData` error instead.
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            // Report errors recorded before this read, as `MiniUart` does;
+            // bytes still in the FIFO are returned by the next one.
+            if let Some(error) = self.take_error() {
+                return Err(error.into());
+            }
+
+            if self.wait_for_byte().is_err() {
+                return Err(io::Error::new(io::ErrorKind::TimedOut,
+                                          "Timed out when reading the first byte."));
+            }
+
+            let mut count = 0;
+            for byte in buf.iter_mut() {
+                if !self.has_byte() {
+                    break;
+                }
+
+                *byte = self.read_byte();
+                count += 1;
+            }
+
+            Ok(count)
+        }
+    }
+
+    impl io::Write for Pl011 {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            for &byte in buf {
+                self.write_byte(byte);
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Pl011::flush(self);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use volatile::mock::{self, RegisterFile};
+    use gpio::Gpio;
+    use uart::ConfigError;
+    use super::Builder;
+
+    fn builder() -> Builder {
+        mock::install(RegisterFile::new());
+        unsafe { Builder::new(Gpio::new(14), Gpio::new(15)) }
+    }
+
+    #[test]
+    fn divisors() {
+        // 48 MHz / (16 * 115200) = 26.042, and 0.042 * 64 rounds to 3.
+        assert_eq!(builder().divisors(), Ok((26, 3)));
+        assert_eq!(builder().actual_baud(), Ok(115_176));
+        assert_eq!(builder().baud(9600).divisors(), Ok((312, 32)));
+        assert_eq!(builder().baud(9600).uart_clock(3_000_000).divisors(), Ok((19, 34)));
+
+        // The extremes of the 16-bit integer divisor.
+        assert_eq!(builder().baud(3_000_000).divisors(), Ok((1, 0)));
+        assert_eq!(builder().baud(46).divisors(), Ok((65217, 25)));
+    }
+
+    #[test]
+    fn divisors_out_of_range() {
+        assert_eq!(builder().baud(0).divisors(), Err(ConfigError::InvalidBaud(0)));
+        assert_eq!(builder().baud(4_000_000).divisors(), Err(ConfigError::InvalidBaud(4_000_000)));
+        assert_eq!(builder().baud(45).divisors(), Err(ConfigError::InvalidBaud(45)));
+        assert_eq!(builder().baud(45).actual_baud(), Err(ConfigError::InvalidBaud(45)));
+    }
+}
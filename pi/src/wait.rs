@@ -0,0 +1,74 @@
+use core::time::Duration;
+
+use volatile::prelude::*;
+
+use timer::{self, Instant};
+
+/// Error returned when a wait's deadline passes before its condition holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimedOut;
+
+/// Spins until `condition` returns `true`.
+#[inline]
+pub fn until<F: FnMut() -> bool>(mut condition: F) {
+    while !condition() {}
+}
+
+/// Spins until `condition` returns `true`. If `deadline` is `Some`, gives up
+/// once it has passed; otherwise, spins indefinitely.
+///
+/// Returns `Ok(())` if the condition held. Returns `Err(TimedOut)` if the
+/// deadline passed first.
+pub fn until_deadline<F>(deadline: Option<Instant>, mut condition: F) -> Result<(), TimedOut>
+    where F: FnMut() -> bool
+{
+    while !condition() {
+        if let Some(deadline) = deadline {
+            if deadline.has_passed() {
+                return Err(TimedOut);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Spins until `condition` returns `true`. If `timeout` is `Some`, gives up
+/// once that much time has passed; otherwise, spins indefinitely. See
+/// `until_deadline`.
+pub fn until_timeout<F>(timeout: Option<Duration>, condition: F) -> Result<(), TimedOut>
+    where F: FnMut() -> bool
+{
+    until_deadline(timeout.map(timer::deadline), condition)
+}
+
+/// Spins until the register `reg` has every bit of `mask` set, giving up once
+/// `deadline` has passed if it is `Some`. See `until_deadline`.
+///
+/// Registers whose reads have side effects, such as `ClearOnRead` ones, are
+/// not `Readable`; poll those with `until` instead.
+pub fn for_mask<T, R>(reg: &R, mask: T, deadline: Option<Instant>) -> Result<(), TimedOut>
+    where R: Readable<T>,
+          T: ::core::ops::BitAnd<Output = T> + PartialEq + Copy
+{
+    until_deadline(deadline, || reg.has_mask(mask))
+}
+
+/// Spins until a value read from the register `reg` satisfies `predicate`,
+/// giving up once `deadline` has passed if it is `Some`. See
+/// `until_deadline`.
+///
+/// Returns the value that satisfied `predicate`.
+pub fn for_value<T, R, P>(reg: &R, deadline: Option<Instant>, mut predicate: P) -> Result<T, TimedOut>
+    where R: Readable<T>,
+          T: Copy,
+          P: FnMut(T) -> bool
+{
+    let mut value = reg.read();
+    until_deadline(deadline, || {
+        value = reg.read();
+        predicate(value)
+    })?;
+
+    Ok(value)
+}
@@ -253,7 +253,7 @@ pub mod io;
 // pub mod path;
 // pub mod process;
 pub mod sync;
-// pub mod time;
+pub mod time;
 // pub mod heap;
 
 // // Platform-abstraction modules
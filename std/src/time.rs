@@ -0,0 +1,159 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Temporal quantification.
+//!
+//! `Instant` counts microseconds since boot, as read from a clock the platform
+//! registers with `set_clock`; `std` has no access to the hardware itself.
+//! There is no real-time clock, so `SystemTime` is not provided.
+
+#![stable(feature = "time", since = "1.3.0")]
+
+use fmt;
+use mem;
+use ops::{Add, Sub, AddAssign, SubAssign};
+use sync::atomic::{AtomicUsize, Ordering};
+
+#[stable(feature = "time", since = "1.3.0")]
+pub use core::time::Duration;
+
+/// The registered clock, as a `fn() -> u64`, or `0` if none is registered.
+static CLOCK: AtomicUsize = AtomicUsize::new(0);
+
+/// Registers `now`, which returns the number of microseconds since boot, as
+/// the clock behind `Instant::now`. The clock must be monotonic.
+#[unstable(feature = "time_clock", issue = "0")]
+pub fn set_clock(now: fn() -> u64) {
+    CLOCK.store(now as usize, Ordering::Release);
+}
+
+/// Reads the registered clock.
+///
+/// # Panics
+///
+/// Panics if no clock has been registered with `set_clock`.
+fn now_micros() -> u64 {
+    match CLOCK.load(Ordering::Acquire) {
+        0 => panic!("std::time: no clock has been registered with `set_clock`"),
+        clock => unsafe { mem::transmute::<usize, fn() -> u64>(clock)() }
+    }
+}
+
+/// Returns the number of whole microseconds in `duration`, or `None` if that
+/// overflows a `u64`.
+fn micros(duration: Duration) -> Option<u64> {
+    duration.as_secs()
+        .checked_mul(1_000_000)?
+        .checked_add(duration.subsec_micros() as u64)
+}
+
+/// A measurement of a monotonically nondecreasing clock.
+/// Opaque and useful only with `Duration`.
+///
+/// Instants are always guaranteed to be no less than any previously measured
+/// instant when created, and are often useful for tasks such as measuring
+/// benchmarks or timing how long an operation takes.
+///
+/// The resolution of an `Instant` is one microsecond.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[stable(feature = "time2", since = "1.8.0")]
+pub struct Instant(u64);
+
+impl Instant {
+    /// Returns an instant corresponding to "now".
+    ///
+    /// # Panics
+    ///
+    /// Panics if no clock has been registered with `set_clock`.
+    #[stable(feature = "time2", since = "1.8.0")]
+    pub fn now() -> Instant {
+        Instant(now_micros())
+    }
+
+    /// Returns the amount of time elapsed from another instant to this one.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `earlier` is later than `self`.
+    #[stable(feature = "time2", since = "1.8.0")]
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        let us = self.0.checked_sub(earlier.0)
+            .expect("supplied instant is later than self");
+        Duration::from_micros(us)
+    }
+
+    /// Returns the amount of time elapsed since this instant was created.
+    #[stable(feature = "time2", since = "1.8.0")]
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().duration_since(*self)
+    }
+
+    /// Returns `Some(t)` where `t` is the time `self + duration` if `t` can be
+    /// represented as `Instant`, `None` otherwise.
+    #[unstable(feature = "time_checked_add", issue = "0")]
+    pub fn checked_add(&self, duration: Duration) -> Option<Instant> {
+        micros(duration).and_then(|us| self.0.checked_add(us)).map(Instant)
+    }
+
+    /// Returns `Some(t)` where `t` is the time `self - duration` if `t` can be
+    /// represented as `Instant`, `None` otherwise.
+    #[unstable(feature = "time_checked_add", issue = "0")]
+    pub fn checked_sub(&self, duration: Duration) -> Option<Instant> {
+        micros(duration).and_then(|us| self.0.checked_sub(us)).map(Instant)
+    }
+}
+
+#[stable(feature = "time2", since = "1.8.0")]
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, other: Duration) -> Instant {
+        self.checked_add(other).expect("overflow when adding duration to instant")
+    }
+}
+
+#[stable(feature = "time_augmented_assignment", since = "1.9.0")]
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, other: Duration) {
+        *self = *self + other;
+    }
+}
+
+#[stable(feature = "time2", since = "1.8.0")]
+impl Sub<Duration> for Instant {
+    type Output = Instant;
+
+    fn sub(self, other: Duration) -> Instant {
+        self.checked_sub(other).expect("overflow when subtracting duration from instant")
+    }
+}
+
+#[stable(feature = "time_augmented_assignment", since = "1.9.0")]
+impl SubAssign<Duration> for Instant {
+    fn sub_assign(&mut self, other: Duration) {
+        *self = *self - other;
+    }
+}
+
+#[stable(feature = "time2", since = "1.8.0")]
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    fn sub(self, other: Instant) -> Duration {
+        self.duration_since(other)
+    }
+}
+
+#[stable(feature = "time2", since = "1.8.0")]
+impl fmt::Debug for Instant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Instant({}us)", self.0)
+    }
+}
@@ -0,0 +1,28 @@
+//! The primitive volatile accesses that every wrapper funnels through.
+//!
+//! With the `mock` feature enabled, accesses are routed to the simulated
+//! register file in `mock` instead of dereferencing the raw pointer.
+
+#[cfg(not(feature = "mock"))]
+#[inline(always)]
+pub(crate) unsafe fn read<T>(ptr: *const T) -> T {
+    ::core::ptr::read_volatile(ptr)
+}
+
+#[cfg(not(feature = "mock"))]
+#[inline(always)]
+pub(crate) unsafe fn write<T>(ptr: *mut T, val: T) {
+    ::core::ptr::write_volatile(ptr, val)
+}
+
+#[cfg(feature = "mock")]
+#[inline(always)]
+pub(crate) unsafe fn read<T>(ptr: *const T) -> T {
+    ::mock::read(ptr)
+}
+
+#[cfg(feature = "mock")]
+#[inline(always)]
+pub(crate) unsafe fn write<T>(ptr: *mut T, val: T) {
+    ::mock::write(ptr, val)
+}
@@ -0,0 +1,150 @@
+/// Describes one field of a register block: its name, its byte offset from
+/// the start of the block, and its size in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterInfo {
+    pub name: &'static str,
+    pub offset: usize,
+    pub size: usize,
+}
+
+/// Trait implemented by register blocks declared with [`register_block!`].
+pub trait RegisterBlock {
+    /// The fields of the block in declaration order.
+    const LAYOUT: &'static [RegisterInfo];
+
+    /// Returns the field covering the byte at `offset` from the start of the
+    /// block, if any.
+    fn register_at(offset: usize) -> Option<&'static RegisterInfo> {
+        Self::LAYOUT.iter().find(|r| r.offset <= offset && offset < r.offset + r.size)
+    }
+}
+
+/// Declares a `#[repr(C)]` register block whose layout is verified at compile
+/// time.
+///
+/// Each field is declared with the byte offset the datasheet gives for it.
+/// The build fails if a field does not start exactly where the previous one
+/// ends or is not suitably aligned for its offset, so any mistake in the
+/// reserved padding between registers is caught. The mismatch is reported as
+/// an "expected an array with a size of 0" type error pointing at the block.
+///
+/// The block implements [`RegisterBlock`], exposing its offset table through
+/// `LAYOUT`.
+///
+/// ```rust,ignore
+/// register_block! {
+///     #[allow(non_snake_case)]
+///     struct Registers {
+///         0x00 => CS: Volatile<u32>,
+///         0x04 => CLO: ReadVolatile<u32>,
+///         0x08 => CHI: ReadVolatile<u32>,
+///         0x0C => COMPARE: [Volatile<u32>; 4],
+///     }
+/// }
+/// ```
+///
+/// A block that leaves out the padding before a register does not build:
+///
+/// ```rust,compile_fail
+/// # #[macro_use] extern crate volatile;
+/// # use volatile::Volatile;
+/// register_block! {
+///     struct Registers {
+///         0x00 => CS: Volatile<u32>,
+///         0x08 => CHI: Volatile<u32>,
+///     }
+/// }
+/// # fn main() {  }
+/// ```
+///
+/// Neither does one placing a register at a misaligned offset:
+///
+/// ```rust,compile_fail
+/// # #[macro_use] extern crate volatile;
+/// # use volatile::Volatile;
+/// register_block! {
+///     struct Registers {
+///         0x00 => LO: Volatile<u16>,
+///         0x02 => HI: Volatile<u32>,
+///     }
+/// }
+/// # fn main() {  }
+/// ```
+#[macro_export]
+macro_rules! register_block {
+    (
+        $(#[$attr:meta])*
+        struct $name:ident {
+            $($offset:tt => $field:ident: $ty:ty),* $(,)*
+        }
+    ) => {
+        $(#[$attr])*
+        #[repr(C)]
+        struct $name {
+            $($field: $ty),*
+        }
+
+        impl $crate::RegisterBlock for $name {
+            const LAYOUT: &'static [$crate::RegisterInfo] = &[$(
+                $crate::RegisterInfo {
+                    name: stringify!($field),
+                    offset: $offset,
+                    size: $crate::__core::mem::size_of::<$ty>(),
+                }
+            ),*];
+        }
+
+        impl $name {
+            #[allow(dead_code)]
+            fn __verify_layout() {
+                register_block!(@check 0; $($offset $ty,)*);
+            }
+        }
+    };
+
+    (@check $end:expr;) => {  };
+
+    (@check $end:expr; $offset:tt $ty:ty, $($rest:tt)*) => {
+        let _: [(); 0] = [(); (($end) != $offset) as usize];
+        let _: [(); 0] = [(); ($offset % $crate::__core::mem::align_of::<$ty>() != 0) as usize];
+        register_block!(@check $offset + $crate::__core::mem::size_of::<$ty>(); $($rest)*);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use {RegisterBlock, RegisterInfo, Volatile, ReadVolatile, Reserved};
+
+    register_block! {
+        #[allow(non_snake_case, dead_code)]
+        struct Registers {
+            0x00 => CS: Volatile<u32>,
+            0x04 => LO: ReadVolatile<u16>,
+            0x06 => __r0: Reserved<u16>,
+            0x08 => COMPARE: [Volatile<u32>; 2],
+            0x10 => DATA: Volatile<u8>,
+        }
+    }
+
+    #[test]
+    fn layout() {
+        assert_eq!(Registers::LAYOUT, &[
+            RegisterInfo { name: "CS", offset: 0x00, size: 4 },
+            RegisterInfo { name: "LO", offset: 0x04, size: 2 },
+            RegisterInfo { name: "__r0", offset: 0x06, size: 2 },
+            RegisterInfo { name: "COMPARE", offset: 0x08, size: 8 },
+            RegisterInfo { name: "DATA", offset: 0x10, size: 1 },
+        ]);
+    }
+
+    #[test]
+    fn register_at() {
+        let name = |offset| Registers::register_at(offset).map(|r| r.name);
+        assert_eq!(name(0x00), Some("CS"));
+        assert_eq!(name(0x03), Some("CS"));
+        assert_eq!(name(0x05), Some("LO"));
+        assert_eq!(name(0x0C), Some("COMPARE"));
+        assert_eq!(name(0x10), Some("DATA"));
+        assert_eq!(name(0x11), None);
+    }
+}
@@ -1,12 +1,33 @@
 #![feature(decl_macro)]
 #![feature(optin_builtin_traits)]
+#![feature(const_fn)]
 
-#![no_std]
+#![cfg_attr(not(feature = "mock"), no_std)]
+
+#[cfg(feature = "mock")]
+extern crate core;
 
 mod traits;
 mod macros;
+mod register;
+mod block;
+mod access;
+mod mmio;
+
+#[cfg(feature = "mock")]
+pub mod mock;
 
 pub use traits::*;
+pub use register::*;
+pub use block::*;
+pub use mmio::Mmio;
+
+#[doc(hidden)]
+pub mod __core {
+    pub use core::{fmt, mem, ops};
+}
+
+use core::cell::UnsafeCell;
 use macros::*;
 
 /// Reexports all of the traits in this crate.
@@ -25,17 +46,33 @@ pub mod prelude {
 /// A wrapper type that enforces **read-only** _volatile_ accesses to a raw
 /// pointer.
 #[repr(C)]
-pub struct ReadVolatile<T>(T);
+pub struct ReadVolatile<T>(UnsafeCell<T>);
 
 /// A wrapper type that enforces _volatile_ (read **or** write) accesses to a
 /// raw pointer.
 #[repr(C)]
-pub struct Volatile<T>(T);
+pub struct Volatile<T>(UnsafeCell<T>);
 
 /// A wrapper type that enforces **write-only** _volatile_ accesses to a raw
 /// pointer.
 #[repr(C)]
-pub struct WriteVolatile<T>(T);
+pub struct WriteVolatile<T>(UnsafeCell<T>);
+
+/// A wrapper type for _write-1-to-clear_ registers.
+///
+/// Reads are _volatile_ and have no side effects. Writing a `1` to a bit clears
+/// it while writing a `0` leaves it untouched, so the only write exposed is
+/// `clear`: a read-modify-write such as `or_mask` would clear every bit that
+/// happened to be set.
+#[repr(C)]
+pub struct W1C<T>(UnsafeCell<T>);
+
+/// A wrapper type for registers whose bits are cleared by reading them.
+///
+/// Since every read has a side effect, reads are only exposed through `take`,
+/// which returns the full value so that no bit is lost to a masked check.
+#[repr(C)]
+pub struct ClearOnRead<T>(UnsafeCell<T>);
 
 /// A wrapper type that prevents read or writes to its value.
 ///
@@ -53,25 +90,55 @@ pub struct Reserved<T>(T);
 pub struct Unique<T>(T);
 
 // Implementations for `ReadVolatile`.
-ptr!(ReadVolatile, |self| &self.0);
-readable!(ReadVolatile, |self| &self.0);
+ptr!(ReadVolatile, |self| self.0.get());
+readable!(ReadVolatile, |self| self.0.get());
 unsafe impl<T: Send> Send for ReadVolatile<T> {  }
 impl<T> !Sync for ReadVolatile<T> {  }
 
 // Implementations for `Volatile`.
-ptr!(Volatile, |self| &self.0);
-readable!(Volatile, |self| &self.0);
-writeable!(Volatile, |self| &mut self.0);
+ptr!(Volatile, |self| self.0.get());
+readable!(Volatile, |self| self.0.get());
+writeable!(Volatile, |self| self.0.get());
 readable_writeable!(Volatile);
 unsafe impl<T: Send> Send for Volatile<T> {  }
 impl<T> !Sync for Volatile<T> {  }
 
 // Implementations for `WriteVolatile`.
-writeable!(WriteVolatile, |self| &mut self.0);
-ptr!(WriteVolatile, |self| &self.0);
+writeable!(WriteVolatile, |self| self.0.get());
+ptr!(WriteVolatile, |self| self.0.get());
 unsafe impl<T: Send> Send for WriteVolatile<T> {  }
 impl<T> !Sync for WriteVolatile<T> {  }
 
+// Implementations for `W1C`.
+ptr!(W1C, |self| self.0.get());
+readable!(W1C, |self| self.0.get());
+unsafe impl<T: Send> Send for W1C<T> {  }
+impl<T> !Sync for W1C<T> {  }
+
+impl<T> W1C<T> {
+    /// Clears the bits that are set in `mask`, leaving every other bit
+    /// untouched. The write is always done using volatile semantics.
+    #[inline(always)]
+    pub fn clear(&self, mask: T) {
+        unsafe { access::write(self.0.get(), mask) }
+    }
+}
+
+// Implementations for `ClearOnRead`.
+ptr!(ClearOnRead, |self| self.0.get());
+unsafe impl<T: Send> Send for ClearOnRead<T> {  }
+impl<T> !Sync for ClearOnRead<T> {  }
+
+impl<T> ClearOnRead<T> {
+    /// Reads and returns the value pointed to by `self`, clearing the bits
+    /// that the hardware clears on read. The read is always done using
+    /// volatile semantics.
+    #[inline(always)]
+    pub fn take(&self) -> T {
+        unsafe { access::read(self.0.get()) }
+    }
+}
+
 // Implementations for `Reserved`.
 ptr!(Reserved, |self| &self.0);
 
@@ -88,7 +155,7 @@ impl<T, R: Readable<T>> Readable<T> for Unique<R> {
 
 impl<T, R: Writeable<T>> Writeable<T> for Unique<R> {
     #[inline(always)]
-    fn inner(&mut self) -> *mut T {
+    fn inner(&self) -> *mut T {
         self.0.inner()
     }
 }
@@ -29,7 +29,7 @@ pub(crate) macro readable($type:ident, |$self:ident| $f:expr) {
 #[doc(hidden)]
 pub(crate) macro writeable($type:ident, |$self:ident| $f:expr) {
     impl<T> Writeable<T> for $type<T> {
-        #[inline(always)] fn inner(&mut $self) -> *mut T { $f }
+        #[inline(always)] fn inner(&$self) -> *mut T { $f }
     }
 }
 
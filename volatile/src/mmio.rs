@@ -0,0 +1,68 @@
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::Deref;
+
+/// A handle to a block of memory-mapped registers of type `B` at a fixed
+/// address.
+///
+/// A handle is nothing more than the block's address: it is `Copy`, can be
+/// constructed in a `const` context, and dereferences to a _shared_ `&B`.
+/// Since every wrapper in this crate performs its writes through `&self`, any
+/// number of handles to the same block may coexist without ever creating a
+/// `&mut` reference to device memory.
+///
+/// ```rust,ignore
+/// const REGISTERS: Mmio<Registers> = unsafe { Mmio::new(TIMER_REG_BASE) };
+///
+/// let low = REGISTERS.CLO.read();
+/// ```
+pub struct Mmio<B> {
+    addr: usize,
+    _block: PhantomData<B>
+}
+
+impl<B> Mmio<B> {
+    /// Returns a handle to the register block `B` at address `addr`.
+    ///
+    /// # Safety
+    ///
+    /// `addr` must be the address of a block of device registers with the
+    /// layout of `B` that stays mapped for the lifetime of the program.
+    #[inline(always)]
+    pub const unsafe fn new(addr: usize) -> Mmio<B> {
+        Mmio { addr: addr, _block: PhantomData }
+    }
+
+    /// Returns the address of the register block.
+    #[inline(always)]
+    pub fn addr(&self) -> usize {
+        self.addr
+    }
+}
+
+impl<B> Deref for Mmio<B> {
+    type Target = B;
+
+    #[inline(always)]
+    fn deref(&self) -> &B {
+        unsafe { &*(self.addr as *const B) }
+    }
+}
+
+impl<B> Clone for Mmio<B> {
+    #[inline(always)]
+    fn clone(&self) -> Mmio<B> {
+        *self
+    }
+}
+
+impl<B> Copy for Mmio<B> {  }
+
+impl<B> fmt::Debug for Mmio<B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Mmio")
+            .field("address", &(self.addr as *const B))
+            .field("size", &::core::mem::size_of::<B>())
+            .finish()
+    }
+}
@@ -0,0 +1,222 @@
+//! A simulated register file for testing drivers off the hardware.
+//!
+//! When the `mock` feature is enabled, every `read` and `write` made through
+//! the wrappers of this crate is routed to the [`Backend`] installed on the
+//! current thread instead of dereferencing the wrapped address. The provided
+//! [`RegisterFile`] backend stores register values by address, returns
+//! scripted values for reads, and logs every access so that tests can assert
+//! on exactly what a driver did:
+//!
+//! ```rust,ignore
+//! let regs = mock::install(mock::RegisterFile::new());
+//! let uart = MiniUart::new();
+//! assert_eq!(regs.borrow().writes_to(BAUD_REG_ADDR), &[270]);
+//! ```
+//!
+//! Accesses made while no backend is installed panic.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::mem;
+use std::rc::Rc;
+
+/// A single register access made through a volatile wrapper.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// A read of `size` bytes at `addr` that returned `value`.
+    Read { addr: usize, size: usize, value: u64 },
+    /// A write of `value`, `size` bytes wide, to `addr`.
+    Write { addr: usize, size: usize, value: u64 },
+}
+
+/// Trait implemented by simulated register backends.
+///
+/// Values are zero-extended to (and truncated from) a `u64`; `size` is the
+/// width of the access in bytes.
+pub trait Backend {
+    /// Performs a read of `size` bytes at address `addr`.
+    fn read(&mut self, addr: usize, size: usize) -> u64;
+
+    /// Performs a write of `value`, `size` bytes wide, to address `addr`.
+    fn write(&mut self, addr: usize, size: usize, value: u64);
+}
+
+/// A simulated register file with scripted reads and an access log.
+///
+/// Registers behave like memory: a read returns the last value written to the
+/// same address, or `0` if the address was never written. Values queued with
+/// [`script`](#method.script) take precedence and are returned by reads in
+/// order until the queue is exhausted.
+#[derive(Debug, Default)]
+pub struct RegisterFile {
+    values: HashMap<usize, u64>,
+    scripts: HashMap<usize, VecDeque<u64>>,
+    log: Vec<Access>,
+}
+
+impl RegisterFile {
+    /// Returns a new, empty register file.
+    pub fn new() -> RegisterFile {
+        RegisterFile::default()
+    }
+
+    /// Sets the current value of the register at `addr` without logging an
+    /// access.
+    pub fn set(&mut self, addr: usize, value: u64) {
+        self.values.insert(addr, value);
+    }
+
+    /// Returns the current value of the register at `addr` without logging an
+    /// access.
+    pub fn get(&self, addr: usize) -> u64 {
+        self.values.get(&addr).cloned().unwrap_or(0)
+    }
+
+    /// Queues `values` to be returned, in order, by the next reads of `addr`.
+    pub fn script(&mut self, addr: usize, values: &[u64]) {
+        self.scripts.entry(addr).or_insert_with(VecDeque::new).extend(values);
+    }
+
+    /// Returns every access made so far, oldest first.
+    pub fn log(&self) -> &[Access] {
+        &self.log
+    }
+
+    /// Returns the values written to `addr` so far, oldest first.
+    pub fn writes_to(&self, addr: usize) -> Vec<u64> {
+        self.log.iter().filter_map(|access| match *access {
+            Access::Write { addr: a, value, .. } if a == addr => Some(value),
+            _ => None
+        }).collect()
+    }
+
+    /// Clears the access log.
+    pub fn clear_log(&mut self) {
+        self.log.clear();
+    }
+}
+
+impl Backend for RegisterFile {
+    fn read(&mut self, addr: usize, size: usize) -> u64 {
+        let value = match self.scripts.get_mut(&addr).and_then(|s| s.pop_front()) {
+            Some(value) => value,
+            None => self.get(addr)
+        };
+
+        self.log.push(Access::Read { addr, size, value });
+        value
+    }
+
+    fn write(&mut self, addr: usize, size: usize, value: u64) {
+        self.log.push(Access::Write { addr, size, value });
+        self.values.insert(addr, value);
+    }
+}
+
+thread_local! {
+    static BACKEND: RefCell<Option<Rc<RefCell<Backend>>>> = RefCell::new(None);
+}
+
+/// Installs `backend` as the current thread's backend, replacing any previous
+/// one. Returns a shared handle to the backend for inspection.
+pub fn install<B: Backend + 'static>(backend: B) -> Rc<RefCell<B>> {
+    let backend = Rc::new(RefCell::new(backend));
+    BACKEND.with(|b| *b.borrow_mut() = Some(backend.clone()));
+    backend
+}
+
+/// Removes the current thread's backend, if any.
+pub fn uninstall() {
+    BACKEND.with(|b| *b.borrow_mut() = None);
+}
+
+fn with_backend<R, F: FnOnce(&mut Backend) -> R>(f: F) -> R {
+    let backend = BACKEND.with(|b| b.borrow().clone());
+    match backend {
+        Some(backend) => f(&mut *backend.borrow_mut()),
+        None => panic!("volatile::mock: register access with no backend installed")
+    }
+}
+
+pub(crate) unsafe fn read<T>(ptr: *const T) -> T {
+    let size = mem::size_of::<T>();
+    let value = with_backend(|b| b.read(ptr as usize, size));
+    match size {
+        1 => mem::transmute_copy(&(value as u8)),
+        2 => mem::transmute_copy(&(value as u16)),
+        4 => mem::transmute_copy(&(value as u32)),
+        8 => mem::transmute_copy(&value),
+        _ => panic!("volatile::mock: unsupported access size {}", size)
+    }
+}
+
+pub(crate) unsafe fn write<T>(ptr: *mut T, val: T) {
+    let size = mem::size_of::<T>();
+    let value = match size {
+        1 => mem::transmute_copy::<T, u8>(&val) as u64,
+        2 => mem::transmute_copy::<T, u16>(&val) as u64,
+        4 => mem::transmute_copy::<T, u32>(&val) as u64,
+        8 => mem::transmute_copy::<T, u64>(&val),
+        _ => panic!("volatile::mock: unsupported access size {}", size)
+    };
+
+    with_backend(|b| b.write(ptr as usize, size, value));
+}
+
+#[cfg(test)]
+mod tests {
+    use prelude::*;
+    use Volatile;
+    use super::{install, uninstall, Access, RegisterFile};
+
+    fn register<T>(addr: usize) -> &'static mut Volatile<T> {
+        unsafe { &mut *(addr as *mut Volatile<T>) }
+    }
+
+    #[test]
+    fn reads_return_last_write() {
+        let regs = install(RegisterFile::new());
+        regs.borrow_mut().set(0x1000, 0xAB);
+
+        assert_eq!(register::<u32>(0x1000).read(), 0xAB);
+        register::<u32>(0x1000).write(0xCD);
+        assert_eq!(register::<u32>(0x1000).read(), 0xCD);
+        assert_eq!(register::<u32>(0x2000).read(), 0);
+        assert_eq!(regs.borrow().get(0x1000), 0xCD);
+    }
+
+    #[test]
+    fn scripted_reads_come_first() {
+        let regs = install(RegisterFile::new());
+        regs.borrow_mut().set(0x1000, 7);
+        regs.borrow_mut().script(0x1000, &[1, 2]);
+
+        let reg = register::<u8>(0x1000);
+        assert_eq!((reg.read(), reg.read(), reg.read()), (1, 2, 7));
+    }
+
+    #[test]
+    fn accesses_are_logged() {
+        let regs = install(RegisterFile::new());
+        register::<u16>(0x1000).write(0x1234);
+        register::<u64>(0x2000).read();
+        register::<u16>(0x1000).write(0x5678);
+
+        assert_eq!(regs.borrow().log(), &[
+            Access::Write { addr: 0x1000, size: 2, value: 0x1234 },
+            Access::Read { addr: 0x2000, size: 8, value: 0 },
+            Access::Write { addr: 0x1000, size: 2, value: 0x5678 },
+        ]);
+        assert_eq!(regs.borrow().writes_to(0x1000), vec![0x1234, 0x5678]);
+
+        regs.borrow_mut().clear_log();
+        assert!(regs.borrow().log().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "no backend installed")]
+    fn access_without_backend_panics() {
+        uninstall();
+        register::<u32>(0x1000).read();
+    }
+}
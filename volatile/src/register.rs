@@ -0,0 +1,453 @@
+use core::ops::{BitAnd, BitOr, Not, Shl, Shr};
+
+/// Trait implemented by the integer types that can back a register.
+pub trait RegisterBits: Copy + PartialEq
+    + BitAnd<Output = Self> + BitOr<Output = Self> + Not<Output = Self>
+    + Shl<usize, Output = Self> + Shr<usize, Output = Self>
+{
+    /// The number of bits in `Self`.
+    const WIDTH: usize;
+
+    /// The value with no bits set.
+    const ZERO: Self;
+
+    /// The value with only the least significant bit set.
+    const ONE: Self;
+
+    /// The value with every bit set.
+    const ONES: Self;
+
+    /// Converts `val` to `Self`, truncating if `Self` is narrower than `u64`.
+    fn from_u64(val: u64) -> Self;
+
+    /// Zero-extends `self` to a `u64`.
+    fn into_u64(self) -> u64;
+}
+
+macro register_bits($($t:ty),*) {
+    $(impl RegisterBits for $t {
+        const WIDTH: usize = ::core::mem::size_of::<$t>() * 8;
+        const ZERO: $t = 0;
+        const ONE: $t = 1;
+        const ONES: $t = !0;
+
+        #[inline(always)] fn from_u64(val: u64) -> $t { val as $t }
+        #[inline(always)] fn into_u64(self) -> u64 { self as u64 }
+    })*
+}
+
+register_bits!(u8, u16, u32, u64);
+
+/// The position of a field inside of a register: `width` bits starting at bit
+/// `offset`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Field {
+    pub offset: usize,
+    pub width: usize,
+}
+
+impl Field {
+    /// Returns a new `Field` spanning `width` bits starting at bit `offset`.
+    #[inline(always)]
+    pub fn new(offset: usize, width: usize) -> Field {
+        Field { offset, width }
+    }
+
+    /// Returns the mask of the bits covered by this field, already shifted to
+    /// the field's offset.
+    #[inline(always)]
+    pub fn mask<T: RegisterBits>(&self) -> T {
+        let ones = if self.width >= T::WIDTH {
+            T::ONES
+        } else {
+            !(T::ONES << self.width)
+        };
+
+        ones << self.offset
+    }
+
+    /// Extracts the value of this field from the register value `bits`. The
+    /// returned value is shifted down to bit 0.
+    #[inline(always)]
+    pub fn get<T: RegisterBits>(&self, bits: T) -> T {
+        (bits & self.mask()) >> self.offset
+    }
+
+    /// Returns `bits` with this field replaced by `value`. Bits of `value`
+    /// that do not fit in the field are discarded.
+    #[inline(always)]
+    pub fn set<T: RegisterBits>(&self, bits: T, value: T) -> T {
+        let mask: T = self.mask();
+        (bits & !mask) | ((value << self.offset) & mask)
+    }
+}
+
+/// Trait implemented by types that can be stored in a register field.
+///
+/// Implementations are provided for `bool` (single bit flags) and the unsigned
+/// integer types. Enumerated field values are declared with the
+/// [`field_enum!`] macro, which implements this trait for the enum.
+pub trait FieldType<T: RegisterBits>: Sized {
+    /// The type a read of the field returns. This is `Option<Self>` for
+    /// enumerations, whose bit patterns need not all be valid.
+    type Read;
+
+    /// Decodes the field bits `bits`, shifted down to bit 0.
+    fn decode(bits: T) -> Self::Read;
+
+    /// Encodes `self` into field bits, unshifted.
+    fn encode(self) -> T;
+}
+
+impl<T: RegisterBits> FieldType<T> for bool {
+    type Read = bool;
+
+    #[inline(always)]
+    fn decode(bits: T) -> bool {
+        bits != T::ZERO
+    }
+
+    #[inline(always)]
+    fn encode(self) -> T {
+        if self { T::ONE } else { T::ZERO }
+    }
+}
+
+macro field_type_int($($t:ty),*) {
+    $(impl<T: RegisterBits> FieldType<T> for $t {
+        type Read = $t;
+
+        #[inline(always)]
+        fn decode(bits: T) -> $t {
+            bits.into_u64() as $t
+        }
+
+        #[inline(always)]
+        fn encode(self) -> T {
+            T::from_u64(self as u64)
+        }
+    })*
+}
+
+field_type_int!(u8, u16, u32, u64);
+
+/// Declares typed register values.
+///
+/// Each register is declared as a `pub struct` wrapping its backing integer,
+/// followed by the register's fields. A field is declared with its access
+/// (`rw`, `r`, or `w`), the names of its getter and/or setter, its bit offset
+/// and width, and its type. Getters return the field's value; setters consume
+/// the register value and return it with the field replaced, so they can be
+/// chained. A field repeated `N` times at consecutive positions is declared
+/// with `name[N]`; its accessors then take the index of the repetition.
+///
+/// The generated types can be wrapped in the volatile wrappers of this crate
+/// directly:
+///
+/// ```rust,ignore
+/// bitfields! {
+///     /// The mini UART line control register.
+///     pub struct Lcr(u8) {
+///         rw data_size, with_data_size @ 0, 2: DataSize;
+///         rw dlab, with_dlab @ 7, 1: bool;
+///     }
+/// }
+///
+/// struct Registers {
+///     LCR_REG: Volatile<Lcr>,
+/// }
+///
+/// registers.LCR_REG.modify(|r| r.with_data_size(DataSize::Bits8));
+/// let dlab = registers.LCR_REG.read().dlab();
+/// ```
+#[macro_export]
+macro_rules! bitfields {
+    ($(
+        $(#[$attr:meta])*
+        pub struct $name:ident($bits:ty) {
+            $($fields:tt)*
+        }
+    )*) => {$(
+        $(#[$attr])*
+        #[repr(C)]
+        #[derive(Clone, Copy, PartialEq, Eq, Default)]
+        pub struct $name($bits);
+
+        impl $name {
+            /// Returns the register value with raw bits `bits`.
+            #[inline(always)]
+            pub fn from_bits(bits: $bits) -> $name {
+                $name(bits)
+            }
+
+            /// Returns the raw bits of this register value.
+            #[inline(always)]
+            pub fn bits(&self) -> $bits {
+                self.0
+            }
+        }
+
+        impl $crate::__core::ops::BitAnd for $name {
+            type Output = $name;
+
+            #[inline(always)]
+            fn bitand(self, rhs: $name) -> $name {
+                $name(self.0 & rhs.0)
+            }
+        }
+
+        impl $crate::__core::ops::BitOr for $name {
+            type Output = $name;
+
+            #[inline(always)]
+            fn bitor(self, rhs: $name) -> $name {
+                $name(self.0 | rhs.0)
+            }
+        }
+
+        impl $crate::__core::fmt::Debug for $name {
+            fn fmt(&self, f: &mut $crate::__core::fmt::Formatter) -> $crate::__core::fmt::Result {
+                write!(f, "{}({:#x})", stringify!($name), self.0)
+            }
+        }
+
+        bitfields!(@field $name, $bits; $($fields)*);
+    )*};
+
+    (@field $name:ident, $bits:ty;) => {  };
+
+    (@field $name:ident, $bits:ty;
+        $(#[$fattr:meta])*
+        rw $get:ident[$count:expr], $set:ident @ $offset:tt, $width:tt: $fty:ty;
+        $($rest:tt)*
+    ) => {
+        bitfields!(@get $name, $bits; $(#[$fattr])* $get[$count] @ $offset, $width: $fty);
+        bitfields!(@set $name, $bits; $(#[$fattr])* $set[$count] @ $offset, $width: $fty);
+        bitfields!(@field $name, $bits; $($rest)*);
+    };
+
+    (@field $name:ident, $bits:ty;
+        $(#[$fattr:meta])*
+        rw $get:ident, $set:ident @ $offset:tt, $width:tt: $fty:ty;
+        $($rest:tt)*
+    ) => {
+        bitfields!(@get $name, $bits; $(#[$fattr])* $get @ $offset, $width: $fty);
+        bitfields!(@set $name, $bits; $(#[$fattr])* $set @ $offset, $width: $fty);
+        bitfields!(@field $name, $bits; $($rest)*);
+    };
+
+    (@field $name:ident, $bits:ty;
+        $(#[$fattr:meta])*
+        r $get:ident[$count:expr] @ $offset:tt, $width:tt: $fty:ty;
+        $($rest:tt)*
+    ) => {
+        bitfields!(@get $name, $bits; $(#[$fattr])* $get[$count] @ $offset, $width: $fty);
+        bitfields!(@field $name, $bits; $($rest)*);
+    };
+
+    (@field $name:ident, $bits:ty;
+        $(#[$fattr:meta])*
+        r $get:ident @ $offset:tt, $width:tt: $fty:ty;
+        $($rest:tt)*
+    ) => {
+        bitfields!(@get $name, $bits; $(#[$fattr])* $get @ $offset, $width: $fty);
+        bitfields!(@field $name, $bits; $($rest)*);
+    };
+
+    (@field $name:ident, $bits:ty;
+        $(#[$fattr:meta])*
+        w $set:ident[$count:expr] @ $offset:tt, $width:tt: $fty:ty;
+        $($rest:tt)*
+    ) => {
+        bitfields!(@set $name, $bits; $(#[$fattr])* $set[$count] @ $offset, $width: $fty);
+        bitfields!(@field $name, $bits; $($rest)*);
+    };
+
+    (@field $name:ident, $bits:ty;
+        $(#[$fattr:meta])*
+        w $set:ident @ $offset:tt, $width:tt: $fty:ty;
+        $($rest:tt)*
+    ) => {
+        bitfields!(@set $name, $bits; $(#[$fattr])* $set @ $offset, $width: $fty);
+        bitfields!(@field $name, $bits; $($rest)*);
+    };
+
+    (@get $name:ident, $bits:ty;
+        $(#[$fattr:meta])* $get:ident[$count:expr] @ $offset:tt, $width:tt: $fty:ty
+    ) => {
+        impl $name {
+            $(#[$fattr])*
+            ///
+            /// # Panics
+            ///
+            /// Panics if `n` is out of bounds.
+            #[inline(always)]
+            pub fn $get(&self, n: usize) -> <$fty as $crate::FieldType<$bits>>::Read {
+                assert!(n < $count);
+                let field = $crate::Field::new($offset + n * $width, $width);
+                <$fty as $crate::FieldType<$bits>>::decode(field.get(self.0))
+            }
+        }
+    };
+
+    (@get $name:ident, $bits:ty;
+        $(#[$fattr:meta])* $get:ident @ $offset:tt, $width:tt: $fty:ty
+    ) => {
+        impl $name {
+            $(#[$fattr])*
+            #[inline(always)]
+            pub fn $get(&self) -> <$fty as $crate::FieldType<$bits>>::Read {
+                let field = $crate::Field::new($offset, $width);
+                <$fty as $crate::FieldType<$bits>>::decode(field.get(self.0))
+            }
+        }
+    };
+
+    (@set $name:ident, $bits:ty;
+        $(#[$fattr:meta])* $set:ident[$count:expr] @ $offset:tt, $width:tt: $fty:ty
+    ) => {
+        impl $name {
+            $(#[$fattr])*
+            ///
+            /// # Panics
+            ///
+            /// Panics if `n` is out of bounds.
+            #[inline(always)]
+            pub fn $set(self, n: usize, value: $fty) -> $name {
+                assert!(n < $count);
+                let field = $crate::Field::new($offset + n * $width, $width);
+                let value = <$fty as $crate::FieldType<$bits>>::encode(value);
+                $name(field.set(self.0, value))
+            }
+        }
+    };
+
+    (@set $name:ident, $bits:ty;
+        $(#[$fattr:meta])* $set:ident @ $offset:tt, $width:tt: $fty:ty
+    ) => {
+        impl $name {
+            $(#[$fattr])*
+            #[inline(always)]
+            pub fn $set(self, value: $fty) -> $name {
+                let field = $crate::Field::new($offset, $width);
+                let value = <$fty as $crate::FieldType<$bits>>::encode(value);
+                $name(field.set(self.0, value))
+            }
+        }
+    };
+}
+
+/// Declares an enumeration of the values a register field can take.
+///
+/// The enumeration implements [`FieldType`] for the register's backing integer
+/// type. Reading a field of this type returns `None` if the field holds a bit
+/// pattern that is not listed.
+///
+/// ```rust,ignore
+/// field_enum! {
+///     /// The mini UART data size.
+///     pub enum DataSize: u8 {
+///         Bits7 = 0b00,
+///         Bits8 = 0b11,
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! field_enum {
+    (
+        $(#[$attr:meta])*
+        pub enum $name:ident: $bits:ty {
+            $($(#[$vattr:meta])* $variant:ident = $value:expr),* $(,)*
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum $name {
+            $($(#[$vattr])* $variant = $value),*
+        }
+
+        impl $crate::FieldType<$bits> for $name {
+            type Read = Option<$name>;
+
+            #[inline(always)]
+            fn decode(bits: $bits) -> Option<$name> {
+                $(if bits == $name::$variant as $bits {
+                    return Some($name::$variant);
+                })*
+
+                None
+            }
+
+            #[inline(always)]
+            fn encode(self) -> $bits {
+                self as $bits
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Field;
+
+    field_enum! {
+        pub enum Mode: u32 {
+            Input = 0b000,
+            Output = 0b001,
+        }
+    }
+
+    bitfields! {
+        pub struct Reg(u32) {
+            rw flag, with_flag @ 0, 1: bool;
+            rw count, with_count @ 4, 4: u8;
+            rw mode[3], with_mode @ 8, 3: Mode;
+        }
+    }
+
+    #[test]
+    fn mask() {
+        assert_eq!(Field::new(0, 1).mask::<u8>(), 0b1);
+        assert_eq!(Field::new(4, 3).mask::<u32>(), 0b111_0000);
+        assert_eq!(Field::new(28, 4).mask::<u32>(), 0xF000_0000);
+        assert_eq!(Field::new(0, 16).mask::<u16>(), 0xFFFF);
+        assert_eq!(Field::new(0, 64).mask::<u64>(), !0);
+    }
+
+    #[test]
+    fn get() {
+        let field = Field::new(4, 4);
+        assert_eq!(field.get(0xABCDu32), 0xC);
+        assert_eq!(field.get(0x0Fu8), 0);
+        assert_eq!(Field::new(0, 32).get(0xDEAD_BEEFu32), 0xDEAD_BEEF);
+    }
+
+    #[test]
+    fn set() {
+        let field = Field::new(4, 4);
+        assert_eq!(field.set(0xFFFFu32, 0x3), 0xFF3F);
+        assert_eq!(field.set(0u32, 0xF), 0xF0);
+
+        // Bits of the value that do not fit in the field are discarded.
+        assert_eq!(field.set(0u32, 0x1F), 0xF0);
+        assert_eq!(Field::new(6, 4).set(0u8, 0xF), 0xC0);
+    }
+
+    #[test]
+    fn bitfields() {
+        let reg = Reg::default().with_flag(true).with_count(9).with_mode(2, Mode::Output);
+        assert_eq!(reg.bits(), 0b001_000_000_1001_0001);
+        assert_eq!((reg.flag(), reg.count()), (true, 9));
+        assert_eq!(reg.mode(0), Some(Mode::Input));
+        assert_eq!(reg.mode(2), Some(Mode::Output));
+
+        // Bit patterns that are not listed in the enumeration read as `None`.
+        assert_eq!(Reg::from_bits(0b111 << 11).mode(1), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn bitfields_index_out_of_bounds() {
+        Reg::default().mode(3);
+    }
+}
@@ -20,7 +20,7 @@ pub trait Readable<T> {
     /// done using volatile semantics.
     #[inline(always)]
     fn read(&self) -> T {
-        unsafe { ::core::ptr::read_volatile(self.inner()) }
+        unsafe { ::access::read(self.inner()) }
     }
 
     /// Returns `true` if the value pointed to by `self` has the mask `mask`.
@@ -38,13 +38,13 @@ pub trait Readable<T> {
 pub trait Writeable<T> {
     /// Returns the inner pointer.
     #[inline(always)]
-    fn inner(&mut self) -> *mut T;
+    fn inner(&self) -> *mut T;
 
     /// Writes the value `val` to the inner address of `self`. The write is
     /// always done using volatile semantics.
     #[inline(always)]
-    fn write(&mut self, val: T) {
-        unsafe { ::core::ptr::write_volatile(self.inner(), val) }
+    fn write(&self, val: T) {
+        unsafe { ::access::write(self.inner(), val) }
     }
 }
 
@@ -55,16 +55,24 @@ pub trait ReadableWriteable<T>: Readable<T> + Writeable<T>
 {
     /// Applies the mask `mask` using `&` to the value referred to by `self`.
     /// This is equivalent to `self.write(self.read() & mask)`.
-    fn and_mask(&mut self, mask: T) {
+    fn and_mask(&self, mask: T) {
         let init_val = self.read();
         self.write(init_val & mask);
     }
 
     /// Applies the mask `mask` using `|` to the value referred to by `self`.
     /// This is equivalent to `self.write(self.read() | mask)`.
-    fn or_mask(&mut self, mask: T) {
+    fn or_mask(&self, mask: T) {
         let init_val = self.read();
         self.write(init_val | mask);
     }
+
+    /// Reads the value referred to by `self`, passes it to `f`, and writes
+    /// back the value `f` returns. This is equivalent to
+    /// `self.write(f(self.read()))`.
+    fn modify<F: FnOnce(T) -> T>(&self, f: F) {
+        let init_val = self.read();
+        self.write(f(init_val));
+    }
 }
 